@@ -0,0 +1,115 @@
+//! Appending a new session to a previously burned multisession disc.
+
+use crate::burn::{burn, BurnOptions};
+use crate::error::BurnError;
+use crate::image::{create_result_image, imported_volume_name};
+use crate::safearray::{make_safearray_dispatch, read_safearray_dispatch};
+use crate::stream::{file_stream, ResultImageStream};
+use crate::util::string_to_bstr;
+use std::path::Path;
+use windows::core::ComInterface;
+use windows::Win32::Storage::Imapi::{
+    IDiscFormat2Data, IFileSystemImage, IFsiDirectoryItem, IMultisession,
+    IMAPI_FORMAT2_DATA_MEDIA_STATE_APPENDABLE, IMAPI_FORMAT2_DATA_MEDIA_STATE_FINALIZED,
+};
+use windows::Win32::System::Com::IDispatch;
+use windows::Win32::System::Ole::SafeArrayDestroy;
+
+/// The multisession continuation interfaces of the loaded disc. A disc with
+/// nothing to continue yields an empty vector.
+pub fn multisession_interfaces(burner: &IDiscFormat2Data) -> Result<Vec<IMultisession>, BurnError> {
+    let psa = unsafe { burner.MultisessionInterfaces()? };
+    read_safearray_dispatch(psa)?
+        .into_iter()
+        .map(|dispatch| dispatch.cast::<IMultisession>().map_err(BurnError::from))
+        .collect()
+}
+
+/// Hands `sessions` to `image` so `ImportFileSystem` can continue the disc
+/// they describe. An empty slice clears the property.
+pub fn set_multisession_interfaces(
+    image: &IFileSystemImage,
+    sessions: &[IMultisession],
+) -> Result<(), BurnError> {
+    let dispatches = sessions
+        .iter()
+        .map(|session| session.cast::<IDispatch>())
+        .collect::<windows::core::Result<Vec<_>>>()?;
+    let psa = make_safearray_dispatch(&dispatches)?;
+    let set = unsafe { image.SetMultisessionInterfaces(psa) };
+    unsafe {
+        let _ = SafeArrayDestroy(psa);
+    }
+    Ok(set?)
+}
+
+/// Builder re-opening a multisession disc for appending: imports the file
+/// system of the prior sessions, lets the caller stage additional content,
+/// then burns it all as a new session.
+pub struct AppendSession {
+    burner: IDiscFormat2Data,
+    image: IFileSystemImage,
+}
+
+impl AppendSession {
+    /// Imports the existing disc content into `image` and prepares it for
+    /// appending through the recorder attached to `burner`.
+    ///
+    /// Fails early with `BurnError::DiscFinalized` when the disc was closed
+    /// by a prior burn, before any staging work is done. Discs with several
+    /// prior sessions import cleanly: `ImportFileSystem` reads the latest
+    /// logical view of the disc, not just the first session.
+    pub fn open(
+        burner: &IDiscFormat2Data,
+        image: IFileSystemImage,
+    ) -> Result<AppendSession, BurnError> {
+        unsafe {
+            let status = burner.CurrentMediaStatus()?;
+            if status.0 & IMAPI_FORMAT2_DATA_MEDIA_STATE_FINALIZED.0 != 0
+                || status.0 & IMAPI_FORMAT2_DATA_MEDIA_STATE_APPENDABLE.0 == 0
+            {
+                return Err(BurnError::DiscFinalized);
+            }
+
+            let interfaces = burner.MultisessionInterfaces()?;
+            // `SetMultisessionInterfaces` copies the array; the caller owns
+            // and must destroy it, like in `set_multisession_interfaces`.
+            let set = image.SetMultisessionInterfaces(interfaces);
+            let _ = SafeArrayDestroy(interfaces);
+            set?;
+            image.ImportFileSystem()?;
+
+            // Keep the label users saw on the disc unless the caller sets a
+            // different one afterwards.
+            if let Some(imported) = imported_volume_name(&image)? {
+                image.SetVolumeName(&string_to_bstr(&imported))?;
+            }
+        }
+        Ok(AppendSession {
+            burner: burner.clone(),
+            image,
+        })
+    }
+
+    /// The imported root directory, for callers that want to stage content
+    /// beyond what the convenience helpers cover.
+    pub fn root(&self) -> Result<IFsiDirectoryItem, BurnError> {
+        Ok(unsafe { self.image.Root()? })
+    }
+
+    /// Stages a local file at the image root under `name`.
+    pub fn add_file(&self, name: &str, source: &Path) -> Result<(), BurnError> {
+        let stream = file_stream(source)?;
+        unsafe { self.root()?.AddFile(&string_to_bstr(name), &stream)? };
+        Ok(())
+    }
+
+    /// Builds the incremental image and burns it as a new session. The
+    /// capacity guard in `create_result_image` still applies, so appending
+    /// more than the remaining space fails before touching the drive.
+    pub fn burn(self, options: BurnOptions) -> Result<(), BurnError> {
+        let result = create_result_image(&self.image)?;
+        let bytes = ResultImageStream::from_result(&result)?.to_bytes()?;
+        burn(&self.burner, || std::io::Cursor::new(&bytes), options)
+    }
+}
@@ -0,0 +1,124 @@
+//! Audio CD authoring through the track-at-once writer.
+
+use crate::error::BurnError;
+use crate::stream::memory_stream;
+use std::io::Read;
+use windows::Win32::Foundation::VARIANT_BOOL;
+use windows::Win32::Storage::Imapi::IDiscFormat2TrackAtOnce;
+
+/// Bytes per red-book audio sector: 588 stereo frames of 16-bit 44.1kHz
+/// samples.
+pub const AUDIO_SECTOR_SIZE: usize = 2352;
+
+// Red-book discs address at most 99 tracks.
+const MAX_AUDIO_TRACKS: usize = 99;
+
+/// Rejects PCM payloads the track-at-once writer would garble: tracks must
+/// be non-empty and hold whole audio sectors.
+fn validate_pcm(pcm: &[u8]) -> Result<(), BurnError> {
+    if pcm.is_empty() {
+        return Err(BurnError::InvalidAudioTrack(
+            "audio tracks must not be empty",
+        ));
+    }
+    if pcm.len() % AUDIO_SECTOR_SIZE != 0 {
+        return Err(BurnError::InvalidAudioTrack(
+            "audio tracks must be whole 2352-byte sectors of 44.1kHz 16-bit stereo PCM",
+        ));
+    }
+    Ok(())
+}
+
+/// Stages 44.1kHz/16-bit stereo PCM tracks and burns them as one
+/// track-at-once session, driving the `PrepareMedia`/`AddAudioTrack`/
+/// `ReleaseMedia` sequence.
+///
+/// Tracks are validated as they are added, so a non-conforming payload is
+/// rejected before the drive is touched.
+pub struct AudioDiscWriter {
+    writer: IDiscFormat2TrackAtOnce,
+    tracks: Vec<Vec<u8>>,
+    finalize: bool,
+}
+
+impl AudioDiscWriter {
+    /// Wraps a track-at-once writer that already has a recorder and client
+    /// name assigned. The session is finalized by default.
+    pub fn new(writer: IDiscFormat2TrackAtOnce) -> Self {
+        AudioDiscWriter {
+            writer,
+            tracks: Vec::new(),
+            finalize: true,
+        }
+    }
+
+    /// Leaves the session open after burning so more tracks can be appended
+    /// later. Maps to `SetDoNotFinalizeMedia`.
+    pub fn do_not_finalize(mut self, skip_finalize: bool) -> Self {
+        self.finalize = !skip_finalize;
+        self
+    }
+
+    /// Reads one track's PCM to the end and stages it, rejecting payloads
+    /// that are empty or not sector-aligned.
+    pub fn add_track(mut self, mut pcm: impl Read) -> Result<Self, BurnError> {
+        if self.tracks.len() == MAX_AUDIO_TRACKS {
+            return Err(BurnError::InvalidAudioTrack(
+                "audio discs hold at most 99 tracks",
+            ));
+        }
+        let mut bytes = Vec::new();
+        pcm.read_to_end(&mut bytes)?;
+        validate_pcm(&bytes)?;
+        self.tracks.push(bytes);
+        Ok(self)
+    }
+
+    /// Burns the staged tracks as one session.
+    pub fn burn(self) -> Result<(), BurnError> {
+        if self.tracks.is_empty() {
+            return Err(BurnError::InvalidAudioTrack(
+                "at least one audio track is required",
+            ));
+        }
+        unsafe {
+            self.writer
+                .SetDoNotFinalizeMedia(VARIANT_BOOL::from(!self.finalize))?;
+            self.writer.PrepareMedia()?;
+            let mut outcome = Ok(());
+            for pcm in &self.tracks {
+                let written =
+                    memory_stream(pcm).and_then(|stream| Ok(self.writer.AddAudioTrack(&stream)?));
+                if written.is_err() {
+                    outcome = written;
+                    break;
+                }
+            }
+            // The media must be released even when a track failed, or the
+            // drive stays locked for exclusive track-at-once access.
+            let released = self.writer.ReleaseMedia();
+            outcome?;
+            released?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pcm_alignment_is_enforced() {
+        assert!(validate_pcm(&vec![0u8; AUDIO_SECTOR_SIZE]).is_ok());
+        assert!(validate_pcm(&vec![0u8; 3 * AUDIO_SECTOR_SIZE]).is_ok());
+        assert!(matches!(
+            validate_pcm(&vec![0u8; AUDIO_SECTOR_SIZE + 1]),
+            Err(BurnError::InvalidAudioTrack(_))
+        ));
+        assert!(matches!(
+            validate_pcm(&[]),
+            Err(BurnError::InvalidAudioTrack(_))
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+//! El Torito boot options with friendly enums and validation.
+
+use crate::error::BurnError;
+use crate::stream::memory_stream;
+use crate::util::string_to_bstr;
+use std::io::Read;
+use windows::Win32::Storage::Imapi::{
+    BootOptions as BootOptionsClass, Emulation12MFloppy, Emulation144MFloppy, Emulation288MFloppy,
+    EmulationHardDisk, EmulationNone, EmulationType, IBootOptions, PlatformEFI, PlatformId,
+    PlatformMac, PlatformPowerPC, PlatformX86,
+};
+use windows::Win32::System::Com::{CoCreateInstance, IStream, CLSCTX_INPROC_SERVER};
+
+/// Friendly spelling of `PlatformId`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BootPlatform {
+    X86,
+    PowerPC,
+    Mac,
+    Efi,
+}
+
+impl From<BootPlatform> for PlatformId {
+    fn from(value: BootPlatform) -> Self {
+        match value {
+            BootPlatform::X86 => PlatformX86,
+            BootPlatform::PowerPC => PlatformPowerPC,
+            BootPlatform::Mac => PlatformMac,
+            BootPlatform::Efi => PlatformEFI,
+        }
+    }
+}
+
+impl From<PlatformId> for BootPlatform {
+    fn from(value: PlatformId) -> Self {
+        match value {
+            PlatformPowerPC => BootPlatform::PowerPC,
+            PlatformMac => BootPlatform::Mac,
+            PlatformEFI => BootPlatform::Efi,
+            _ => BootPlatform::X86,
+        }
+    }
+}
+
+/// Friendly spelling of `EmulationType`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BootEmulation {
+    None,
+    Floppy12,
+    Floppy144,
+    Floppy288,
+    HardDisk,
+}
+
+impl From<BootEmulation> for EmulationType {
+    fn from(value: BootEmulation) -> Self {
+        match value {
+            BootEmulation::None => EmulationNone,
+            BootEmulation::Floppy12 => Emulation12MFloppy,
+            BootEmulation::Floppy144 => Emulation144MFloppy,
+            BootEmulation::Floppy288 => Emulation288MFloppy,
+            BootEmulation::HardDisk => EmulationHardDisk,
+        }
+    }
+}
+
+impl From<EmulationType> for BootEmulation {
+    fn from(value: EmulationType) -> Self {
+        match value {
+            Emulation12MFloppy => BootEmulation::Floppy12,
+            Emulation144MFloppy => BootEmulation::Floppy144,
+            Emulation288MFloppy => BootEmulation::Floppy288,
+            EmulationHardDisk => BootEmulation::HardDisk,
+            _ => BootEmulation::None,
+        }
+    }
+}
+
+/// Builder applying a validated platform/emulation pair and a boot image
+/// stream to an `IBootOptions`.
+pub struct BootImageBuilder {
+    platform: BootPlatform,
+    emulation: BootEmulation,
+    image: IStream,
+}
+
+impl BootImageBuilder {
+    /// Starts from the boot image content; defaults to a BIOS (x86,
+    /// no-emulation) entry.
+    pub fn new(image: IStream) -> Self {
+        BootImageBuilder {
+            platform: BootPlatform::X86,
+            emulation: BootEmulation::None,
+            image,
+        }
+    }
+
+    pub fn platform(mut self, platform: BootPlatform) -> Self {
+        self.platform = platform;
+        self
+    }
+
+    pub fn emulation(mut self, emulation: BootEmulation) -> Self {
+        self.emulation = emulation;
+        self
+    }
+
+    /// Applies the configuration to `options` after validating the El Torito
+    /// combination. EFI entries are always no-emulation.
+    pub fn apply(self, options: &IBootOptions) -> Result<(), BurnError> {
+        if self.platform == BootPlatform::Efi && self.emulation != BootEmulation::None {
+            return Err(BurnError::InvalidBootOptions(
+                "EFI boot entries must use no-emulation mode",
+            ));
+        }
+        unsafe {
+            options.SetPlatformId(self.platform.into())?;
+            options.SetEmulation(self.emulation.into())?;
+            options.AssignBootImage(&self.image)?;
+        }
+        Ok(())
+    }
+}
+
+/// Owning counterpart of `BootImageBuilder`: creates its own `IBootOptions`
+/// and configures it through a fluent chain, ready to hand to
+/// `IFileSystemImage::SetBootImageOptions`.
+pub struct BootOptions {
+    options: IBootOptions,
+    manufacturer: Option<String>,
+    platform: BootPlatform,
+    emulation: BootEmulation,
+    image_assigned: bool,
+}
+
+impl BootOptions {
+    /// Creates a fresh `IBootOptions`, defaulting to a BIOS (x86,
+    /// no-emulation) entry.
+    pub fn new() -> Result<BootOptions, BurnError> {
+        let options = unsafe { CoCreateInstance(&BootOptionsClass, None, CLSCTX_INPROC_SERVER)? };
+        Ok(BootOptions {
+            options,
+            manufacturer: None,
+            platform: BootPlatform::X86,
+            emulation: BootEmulation::None,
+            image_assigned: false,
+        })
+    }
+
+    /// The manufacturer string recorded in the boot catalog.
+    pub fn manufacturer(mut self, manufacturer: &str) -> Self {
+        self.manufacturer = Some(manufacturer.to_string());
+        self
+    }
+
+    pub fn platform(mut self, platform: BootPlatform) -> Self {
+        self.platform = platform;
+        self
+    }
+
+    pub fn emulation(mut self, emulation: BootEmulation) -> Self {
+        self.emulation = emulation;
+        self
+    }
+
+    /// Reads `source` to its end and assigns it as the boot image.
+    pub fn boot_image(mut self, mut source: impl Read) -> Result<Self, BurnError> {
+        let mut bytes = Vec::new();
+        source.read_to_end(&mut bytes)?;
+        let stream = memory_stream(&bytes)?;
+        unsafe { self.options.AssignBootImage(&stream)? };
+        self.image_assigned = true;
+        Ok(self)
+    }
+
+    /// Size of the assigned boot image, as IMAPI sees it.
+    pub fn image_size(&self) -> Result<u32, BurnError> {
+        if !self.image_assigned {
+            return Err(BurnError::InvalidBootOptions("no boot image assigned"));
+        }
+        Ok(unsafe { self.options.ImageSize()? })
+    }
+
+    /// Applies the remaining settings after validating the El Torito
+    /// combination, and hands over the configured `IBootOptions`.
+    pub fn build(self) -> Result<IBootOptions, BurnError> {
+        if !self.image_assigned {
+            return Err(BurnError::InvalidBootOptions("no boot image assigned"));
+        }
+        if self.platform == BootPlatform::Efi && self.emulation != BootEmulation::None {
+            return Err(BurnError::InvalidBootOptions(
+                "EFI boot entries must use no-emulation mode",
+            ));
+        }
+        unsafe {
+            if let Some(manufacturer) = &self.manufacturer {
+                self.options
+                    .SetManufacturer(&string_to_bstr(manufacturer))?;
+            }
+            self.options.SetPlatformId(self.platform.into())?;
+            self.options.SetEmulation(self.emulation.into())?;
+        }
+        Ok(self.options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn enum_round_trips() {
+        for platform in [
+            BootPlatform::X86,
+            BootPlatform::PowerPC,
+            BootPlatform::Mac,
+            BootPlatform::Efi,
+        ] {
+            assert_eq!(BootPlatform::from(PlatformId::from(platform)), platform);
+        }
+        for emulation in [
+            BootEmulation::None,
+            BootEmulation::Floppy12,
+            BootEmulation::Floppy144,
+            BootEmulation::Floppy288,
+            BootEmulation::HardDisk,
+        ] {
+            assert_eq!(
+                BootEmulation::from(EmulationType::from(emulation)),
+                emulation
+            );
+        }
+    }
+}
@@ -0,0 +1,521 @@
+//! Burn entry points for the `IDiscFormat2Data` writer.
+
+use crate::com::ensure_apartment;
+use crate::error::BurnError;
+use crate::events::{DataEventSink, EventCookie};
+use crate::factory::{new_disc_recorder2, new_format2_data};
+use crate::image::{create_result_image, set_capacity, Capacity};
+use crate::media::{
+    is_current_media_supported, is_recorder_supported, media_write_mode, MediaGeneration,
+    MediaType, WriteMode,
+};
+use crate::progress::BurnProgress;
+use crate::scsi::{get_mode_page, set_mode_page, SECTOR_SIZE};
+use crate::sense::BurnFailure;
+use crate::speed::{request_write_speed, supported_write_speeds};
+use crate::stream::{memory_stream, ReadSeekStream, ResultImageStream};
+use crate::util::string_to_bstr;
+use crate::verify::{set_verification, VerificationLevel};
+use log::{error, info, warn};
+use std::io::Read;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use windows::core::ComInterface;
+use windows::Win32::Foundation::VARIANT_BOOL;
+use windows::Win32::Storage::Imapi::{
+    DDiscFormat2DataEvents, IDiscFormat2, IDiscFormat2Data, IDiscRecorder2, IDiscRecorder2Ex,
+    IFileSystemImage, IMAPI_MODE_PAGE_REQUEST_TYPE_CHANGEABLE_VALUES,
+    IMAPI_MODE_PAGE_REQUEST_TYPE_CURRENT_VALUES, IMAPI_MODE_PAGE_TYPE_WRITE_PARAMETERS,
+};
+
+// Test-write bit in byte 2 of the Write Parameters mode page.
+const TEST_WRITE_BIT: u8 = 0x10;
+
+/// How many attempts to make and which failure classes deserve another one.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryStrategy {
+    pub max_attempts: u32,
+    pub retry_power_calibration: bool,
+    pub retry_buffer_underrun: bool,
+}
+
+impl Default for RetryStrategy {
+    fn default() -> Self {
+        RetryStrategy {
+            max_attempts: 3,
+            retry_power_calibration: true,
+            retry_buffer_underrun: true,
+        }
+    }
+}
+
+impl RetryStrategy {
+    fn is_retryable(&self, error: &BurnError) -> bool {
+        match error {
+            BurnError::PowerCalibration(_) => self.retry_power_calibration,
+            BurnError::BufferUnderrun(_) => self.retry_buffer_underrun,
+            _ => false,
+        }
+    }
+}
+
+/// Options controlling a data burn.
+#[derive(Clone, Debug, Default)]
+pub struct BurnOptions {
+    /// Name IMAPI shows to other clients contending for the drive. `None`
+    /// derives it from the current executable.
+    pub client_name: Option<String>,
+    /// Dry-run the burn through the drive test-write mode instead of
+    /// actually marking the media. Fails with
+    /// `BurnError::SimulationNotSupported` when the drive can't do it.
+    pub simulate: bool,
+    /// Retry policy for recoverable failures.
+    pub retry: RetryStrategy,
+    /// Whether to close the media after the burn. `None` picks a default
+    /// from the media write mode: write-once media is finalized, rewritable
+    /// media is left open.
+    pub finalize: Option<bool>,
+    /// Media token captured at check time. When set, it's re-validated right
+    /// before the write and the burn fails with `MediaChanged` if the disc
+    /// was swapped in between.
+    pub media_generation: Option<MediaGeneration>,
+    /// Requested write speed in sectors per second, validated against the
+    /// drive's supported speeds. `None` leaves the drive default.
+    pub speed: Option<i32>,
+    /// Drive-side verification level applied before the write. `None`
+    /// leaves whatever the writer is configured with.
+    pub verification: Option<VerificationLevel>,
+    /// Eject the tray once the burn finished.
+    pub eject_after_burn: bool,
+}
+
+impl BurnOptions {
+    /// Overrides the derived client name.
+    pub fn with_client_name(mut self, name: &str) -> Self {
+        self.client_name = Some(name.to_string());
+        self
+    }
+}
+
+/// Client name used when the executable name cannot be determined.
+const FALLBACK_CLIENT_NAME: &str = "imapi-utils";
+// Longest client name IMAPI accepts.
+const MAX_CLIENT_NAME_LENGTH: usize = 126;
+
+// The configured client name, or one derived from the current executable's
+// file stem, clamped to the IMAPI length limit. IMAPI misbehaves quietly
+// when the name is left blank, so an empty override also falls back.
+fn effective_client_name(options: &BurnOptions) -> String {
+    let name = options
+        .client_name
+        .clone()
+        .or_else(|| {
+            std::env::current_exe().ok().and_then(|exe| {
+                exe.file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+            })
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| FALLBACK_CLIENT_NAME.to_string());
+    if name.chars().count() > MAX_CLIENT_NAME_LENGTH {
+        name.chars().take(MAX_CLIENT_NAME_LENGTH).collect()
+    } else {
+        name
+    }
+}
+
+/// RAII guard flipping the test-write bit of the Write Parameters mode page,
+/// restoring the original page when dropped.
+struct TestWriteGuard {
+    recorder: IDiscRecorder2Ex,
+    original: Vec<u8>,
+}
+
+impl TestWriteGuard {
+    fn enable(recorder: &IDiscRecorder2Ex) -> Result<TestWriteGuard, BurnError> {
+        // A drive supports test writes when the bit is changeable.
+        let changeable = get_mode_page(
+            recorder,
+            IMAPI_MODE_PAGE_TYPE_WRITE_PARAMETERS,
+            IMAPI_MODE_PAGE_REQUEST_TYPE_CHANGEABLE_VALUES,
+        )?;
+        if changeable
+            .get(2)
+            .map_or(true, |byte| byte & TEST_WRITE_BIT == 0)
+        {
+            return Err(BurnError::SimulationNotSupported);
+        }
+
+        let original = get_mode_page(
+            recorder,
+            IMAPI_MODE_PAGE_TYPE_WRITE_PARAMETERS,
+            IMAPI_MODE_PAGE_REQUEST_TYPE_CURRENT_VALUES,
+        )?;
+        let mut modified = original.clone();
+        match modified.get_mut(2) {
+            Some(byte) => *byte |= TEST_WRITE_BIT,
+            // The changeable page said the bit exists, so a current page
+            // without byte 2 is a drive bug, not a missing feature.
+            None => {
+                return Err(BurnError::MalformedResponse(
+                    "the Write Parameters mode page is shorter than its header",
+                ))
+            }
+        }
+        set_mode_page(
+            recorder,
+            IMAPI_MODE_PAGE_REQUEST_TYPE_CURRENT_VALUES,
+            &modified,
+        )?;
+
+        Ok(TestWriteGuard {
+            recorder: recorder.clone(),
+            original,
+        })
+    }
+}
+
+impl Drop for TestWriteGuard {
+    fn drop(&mut self) {
+        if let Err(err) = set_mode_page(
+            &self.recorder,
+            IMAPI_MODE_PAGE_REQUEST_TYPE_CURRENT_VALUES,
+            &self.original,
+        ) {
+            error!("Failed to restore the Write Parameters mode page: {}", err);
+        }
+    }
+}
+
+/// Burns the source to the recorder attached to `burner`, honoring
+/// `options`. Simulation is set up before the first attempt and the original
+/// mode page is restored once the burn (simulated or not) finished.
+pub fn burn<R, F>(
+    burner: &IDiscFormat2Data,
+    source: F,
+    options: BurnOptions,
+) -> Result<(), BurnError>
+where
+    R: Read,
+    F: FnMut() -> R,
+{
+    unsafe { burner.SetClientName(&string_to_bstr(&effective_client_name(&options)))? };
+    let _test_write = if options.simulate {
+        let recorder: IDiscRecorder2Ex = unsafe { burner.Recorder()?.cast()? };
+        Some(TestWriteGuard::enable(&recorder)?)
+    } else {
+        None
+    };
+    let force_close = match options.finalize {
+        Some(value) => value,
+        None => media_write_mode(burner)? == WriteMode::WriteOnce,
+    };
+    unsafe { burner.SetForceMediaToBeClosed(VARIANT_BOOL::from(force_close))? };
+    if let Some(generation) = options.media_generation {
+        let recorder: IDiscRecorder2Ex = unsafe { burner.Recorder()?.cast()? };
+        generation.revalidate(&recorder)?;
+    }
+    if let Some(speed) = options.speed {
+        request_write_speed(burner, speed)?;
+    }
+    if let Some(level) = options.verification {
+        set_verification(burner, level)?;
+    }
+    burn_with_retry(burner, source, options.retry)?;
+    if options.eject_after_burn {
+        // Logged rather than propagated: a stuck tray must not mask the
+        // burn that just succeeded.
+        if let Err(err) = unsafe { burner.Recorder().and_then(|r| r.EjectMedia()) } {
+            warn!("burn succeeded but ejecting the media failed: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// Like `burn`, but additionally streams `BurnProgress` notifications to
+/// `progress` while the burn runs. Drives doing inline verification report
+/// the `Verifying` phase through the same callback.
+pub fn burn_with_progress<R, F, P>(
+    burner: &IDiscFormat2Data,
+    source: F,
+    options: BurnOptions,
+    progress: P,
+) -> Result<(), BurnError>
+where
+    R: Read,
+    F: FnMut() -> R,
+    P: FnMut(BurnProgress) + Send + 'static,
+{
+    ensure_apartment()?;
+    let sink: DDiscFormat2DataEvents = DataEventSink::new(Box::new(progress)).into();
+    let _cookie = EventCookie::advise::<DDiscFormat2DataEvents, _>(burner, &sink.cast()?)?;
+    burn(burner, source, options)
+}
+
+/// Burns the source to the recorder attached to `burner`, stepping down to
+/// the next lower supported write speed after each recoverable failure.
+///
+/// `source` is a factory rather than a stream because every attempt needs to
+/// re-stream the data from the start.
+pub fn burn_with_retry<R, F>(
+    burner: &IDiscFormat2Data,
+    mut source: F,
+    strategy: RetryStrategy,
+) -> Result<(), BurnError>
+where
+    R: Read,
+    F: FnMut() -> R,
+{
+    // Fastest first, so stepping down means moving towards the end.
+    let speeds = supported_write_speeds(burner)?;
+    let recorder: Option<IDiscRecorder2Ex> = unsafe { burner.Recorder() }
+        .ok()
+        .and_then(|r| r.cast().ok());
+    // Step down from the speed the writer is actually configured with, not
+    // the fastest supported one: a caller-requested slow speed must never be
+    // raised by a retry.
+    let mut speed_index = unsafe { burner.CurrentWriteSpeed() }
+        .ok()
+        .and_then(|current| speeds.iter().position(|&speed| speed == current))
+        .unwrap_or(0);
+
+    let max_attempts = strategy.max_attempts.max(1);
+    for attempt in 1..=max_attempts {
+        info!("Burn attempt {} of {}", attempt, max_attempts);
+
+        let mut bytes = Vec::new();
+        source().read_to_end(&mut bytes)?;
+        let stream = memory_stream(&bytes)?;
+
+        match unsafe { burner.Write(&stream) } {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                let failure = BurnFailure::capture(err, recorder.as_ref());
+                warn!("{}", failure);
+                let classified = failure.into_error();
+                if attempt == max_attempts || !strategy.is_retryable(&classified) {
+                    return Err(classified);
+                }
+                // Step down to the next lower supported speed before trying
+                // again.
+                if speed_index + 1 < speeds.len() {
+                    speed_index += 1;
+                    let speed = speeds[speed_index];
+                    info!("Retrying at {} sectors per second", speed);
+                    unsafe { burner.SetWriteSpeed(speed, VARIANT_BOOL::from(false))? };
+                }
+            }
+        }
+    }
+
+    // The loop either returned the success or the last classified error.
+    unreachable!("burn_with_retry loop always returns");
+}
+
+/// Configures `burner` so the next write closes the current session while
+/// leaving the disc appendable, i.e. readable on other machines without
+/// giving up further sessions.
+///
+/// The session/disc distinction only exists on multisession capable
+/// write-once media: CD-R, DVD-R, DVD+R (single and dual layer) and BD-R.
+/// Rewritable and packet-written media have no sessions to close, and for
+/// those this fails with `BurnError::Unsupported`.
+pub fn close_session(burner: &IDiscFormat2Data) -> Result<(), BurnError> {
+    let media = MediaType::from(unsafe { burner.CurrentPhysicalMediaType()? });
+    match media {
+        MediaType::CdR
+        | MediaType::DvdDashR
+        | MediaType::DvdDashRDualLayer
+        | MediaType::DvdPlusR
+        | MediaType::DvdPlusRDualLayer
+        | MediaType::BdR => {}
+        _ => return Err(BurnError::Unsupported("session closing on this media")),
+    }
+    unsafe {
+        // Leaving the media open makes IMAPI close only the session at the
+        // end of the write; consumer compatibility mode keeps that session
+        // close readable in standalone players for DVD+R.
+        burner.SetForceMediaToBeClosed(VARIANT_BOOL::from(false))?;
+        burner.SetDisableConsumerDvdCompatibilityMode(VARIANT_BOOL::from(false))?;
+    }
+    Ok(())
+}
+
+/// One-shot "burn this ISO to this drive" entry point: resolves the
+/// recorder from its IMAPI unique id, creates and configures the writer,
+/// and burns the image honoring `options` (speed, finalization, eject).
+///
+/// Drive-condition failures stay matchable through `BurnError::imapi()`.
+pub fn burn_iso(recorder_id: &str, iso: &Path, options: BurnOptions) -> Result<(), BurnError> {
+    ensure_apartment()?;
+    let recorder = new_disc_recorder2()?;
+    unsafe { recorder.InitializeDiscRecorder(&string_to_bstr(recorder_id))? };
+
+    let burner = new_format2_data()?;
+    unsafe { burner.SetRecorder(&recorder)? };
+
+    // Pre-flight before staging the whole ISO in memory.
+    let format: IDiscFormat2 = burner.cast()?;
+    if !is_recorder_supported(&format, &recorder)? {
+        return Err(BurnError::Unsupported("the drive cannot do data burns"));
+    }
+    if !is_current_media_supported(&format, &recorder)? {
+        return Err(BurnError::Unsupported(
+            "the loaded media cannot take a data burn",
+        ));
+    }
+
+    // The retry loop re-streams from the start on every attempt, so the
+    // image is staged in memory once up front.
+    let bytes = std::fs::read(iso)?;
+    burn(&burner, || std::io::Cursor::new(&bytes), options)
+}
+
+/// Builds the result image of a staged `IFileSystemImage` and burns it in
+/// one call: configures the image capacity from the drive, creates the
+/// result, cocreates and wires an `IDiscFormat2Data`, and writes.
+///
+/// The staged content is checked against the media capacity before the
+/// drive is touched, so an oversized image fails fast instead of mid-burn.
+pub fn burn_image(
+    recorder: &IDiscRecorder2,
+    image: &IFileSystemImage,
+    options: BurnOptions,
+) -> Result<(), BurnError> {
+    ensure_apartment()?;
+    set_capacity(image, Capacity::FromDevice(recorder.clone()))?;
+    let result = create_result_image(image)?;
+    unsafe {
+        let free_blocks = i64::from(image.FreeMediaBlocks()?).max(0);
+        if result.TotalBlocks()? > free_blocks {
+            return Err(BurnError::Unsupported(
+                "the staged image does not fit the loaded media",
+            ));
+        }
+    }
+
+    let burner = new_format2_data()?;
+    unsafe { burner.SetRecorder(recorder)? };
+
+    // The retry loop re-streams from the start on every attempt, so the
+    // image is staged in memory once up front.
+    let bytes = ResultImageStream::from_result(&result)?.to_bytes()?;
+    burn(&burner, || std::io::Cursor::new(&bytes), options)
+}
+
+/// Burns a prebuilt `.iso` file to the disc in `recorder`.
+///
+/// This is the shortest path from an image on disk to a burned disc: it
+/// creates and configures the `IDiscFormat2Data` writer internally, so the
+/// caller needs no IMAPI knowledge. The ISO size is validated against the
+/// free capacity of the loaded media before the drive is touched, and burn
+/// progress is streamed to `progress`.
+pub fn burn_iso_file<P>(
+    recorder: &IDiscRecorder2,
+    iso_path: &Path,
+    progress: P,
+) -> Result<(), BurnError>
+where
+    P: FnMut(BurnProgress) + Send + 'static,
+{
+    ensure_apartment()?;
+
+    let burner = new_format2_data()?;
+    unsafe { burner.SetRecorder(recorder)? };
+
+    let iso = std::fs::File::open(iso_path)?;
+    let iso_bytes = iso.metadata()?.len();
+    let free_bytes = unsafe { burner.FreeSectorsOnMedia()? } as u64 * SECTOR_SIZE as u64;
+    if iso_bytes > free_bytes {
+        return Err(BurnError::Unsupported("the ISO is larger than the media"));
+    }
+
+    let sink: DDiscFormat2DataEvents = DataEventSink::new(Box::new(progress)).into();
+    let _cookie = EventCookie::advise::<DDiscFormat2DataEvents, _>(&burner, &sink.cast()?)?;
+    let stream = ReadSeekStream::new(iso).into_stream();
+    // Capture drive sense data like the other burn paths, so failures here
+    // classify into the same typed errors.
+    let recorder_ex: Option<IDiscRecorder2Ex> = recorder.cast().ok();
+    unsafe { burner.Write(&stream) }
+        .map_err(|err| BurnFailure::capture(err, recorder_ex.as_ref()).into_error())
+}
+
+/// Like `burn_with_progress`, but delivers progress through an `mpsc`
+/// channel instead of a closure, so consumption can happen on another
+/// thread (typically a UI loop) without wrapping the handler in `Send`
+/// plumbing.
+///
+/// The sink sends from the COM thread driving the burn; the receiver should
+/// drain promptly, since an unbounded backlog only costs memory but a
+/// deliberately bounded consumer blocking the callback stalls the burn
+/// event loop. A dropped receiver is harmless: the send errors are ignored
+/// and the burn continues without progress reporting.
+pub fn burn_with_channel<R, F>(
+    burner: &IDiscFormat2Data,
+    source: F,
+    options: BurnOptions,
+    sender: Sender<BurnProgress>,
+) -> Result<(), BurnError>
+where
+    R: Read,
+    F: FnMut() -> R,
+{
+    burn_with_progress(burner, source, options, move |progress| {
+        let _ = sender.send(progress);
+    })
+}
+
+/// Whether the next write on `burner` will finalize the disc, combining the
+/// current `ForceMediaToBeClosed` setting with the media write mode. Pure
+/// query: nothing on the writer or the drive is changed.
+///
+/// A UI can use this to warn that the burn closes the disc for good before
+/// the user commits to it.
+pub fn would_finalize(burner: &IDiscFormat2Data) -> Result<bool, BurnError> {
+    if unsafe { burner.ForceMediaToBeClosed()? }.as_bool() {
+        return Ok(true);
+    }
+    // Without the force flag, write-once media still gets closed when
+    // consumer compatibility mode is active (the IMAPI default); rewritable
+    // media never is.
+    Ok(media_write_mode(burner)? == WriteMode::WriteOnce
+        && !unsafe { burner.DisableConsumerDvdCompatibilityMode()? }.as_bool())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn client_name_defaults() {
+        // An explicit override wins as-is.
+        let named = BurnOptions::default().with_client_name("backup tool");
+        assert_eq!(effective_client_name(&named), "backup tool");
+
+        // Over-long names are clamped to the IMAPI limit.
+        let long = BurnOptions::default().with_client_name(&"x".repeat(200));
+        assert_eq!(
+            effective_client_name(&long).chars().count(),
+            MAX_CLIENT_NAME_LENGTH
+        );
+
+        // An empty override is as bad as none; something non-blank always
+        // comes out.
+        let blank = BurnOptions::default().with_client_name("");
+        assert!(!effective_client_name(&blank).is_empty());
+    }
+
+    #[test]
+    fn retryable_classes() {
+        let strategy = RetryStrategy::default();
+        assert!(strategy.is_retryable(&BurnError::PowerCalibration(None)));
+        assert!(strategy.is_retryable(&BurnError::BufferUnderrun(None)));
+        assert!(!strategy.is_retryable(&BurnError::CapacityNotSet));
+
+        let no_underrun = RetryStrategy {
+            retry_buffer_underrun: false,
+            ..RetryStrategy::default()
+        };
+        assert!(!no_underrun.is_retryable(&BurnError::BufferUnderrun(None)));
+    }
+}
@@ -0,0 +1,57 @@
+//! `QueryInterface`-based casts between the format interfaces.
+//!
+//! `IDiscFormat2Data`, `IDiscFormat2Erase`, `IDiscFormat2TrackAtOnce` and
+//! `IDiscFormat2RawCD` all inherit `IDiscFormat2`, so a generic format
+//! object can be probed for its concrete capability with a cast.
+
+use crate::error::BurnError;
+use windows::core::ComInterface;
+use windows::Win32::Storage::Imapi::{
+    IDiscFormat2, IDiscFormat2Data, IDiscFormat2Erase, IDiscFormat2RawCD, IDiscFormat2TrackAtOnce,
+};
+
+/// Upcasts any format object to the shared `IDiscFormat2` base, for the
+/// media-support queries every writer exposes.
+pub fn as_format2(object: &impl ComInterface) -> Result<IDiscFormat2, BurnError> {
+    Ok(object.cast()?)
+}
+
+/// The data writer behind `object`, or `None` when it is another format.
+pub fn try_as_data(object: &impl ComInterface) -> Option<IDiscFormat2Data> {
+    object.cast().ok()
+}
+
+/// The erase format behind `object`, or `None` when it is another format.
+pub fn try_as_erase(object: &impl ComInterface) -> Option<IDiscFormat2Erase> {
+    object.cast().ok()
+}
+
+/// The track-at-once writer behind `object`, or `None` when it is another
+/// format.
+pub fn try_as_tao(object: &impl ComInterface) -> Option<IDiscFormat2TrackAtOnce> {
+    object.cast().ok()
+}
+
+/// The raw CD writer behind `object`, or `None` when it is another format.
+pub fn try_as_raw_cd(object: &impl ComInterface) -> Option<IDiscFormat2RawCD> {
+    object.cast().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::com::ComApartment;
+    use crate::mock::MockRecorderBuilder;
+
+    #[test]
+    fn unsupported_casts_are_rejected() {
+        let _com = ComApartment::enter().unwrap();
+        // A recorder is not a format object, so every probe must miss.
+        let (recorder, _calls) = MockRecorderBuilder::new().build();
+        assert!(as_format2(&recorder).is_err());
+        assert!(try_as_data(&recorder).is_none());
+        assert!(try_as_erase(&recorder).is_none());
+        assert!(try_as_tao(&recorder).is_none());
+        assert!(try_as_raw_cd(&recorder).is_none());
+    }
+}
@@ -0,0 +1,147 @@
+//! COM apartment lifetime management for the calling thread.
+
+use crate::error::BurnError;
+use std::cell::Cell;
+use std::marker::PhantomData;
+use windows::core::ComInterface;
+use windows::Win32::Storage::Imapi::IDiscRecorder2;
+use windows::Win32::System::Com::{
+    CoGetInterfaceAndReleaseStream, CoInitializeEx, CoMarshalInterThreadInterfaceInStream,
+    CoUninitialize, IStream, COINIT_APARTMENTTHREADED,
+};
+
+thread_local! {
+    // Nesting depth of `ComApartment` guards on this thread.
+    static APARTMENT_DEPTH: Cell<u32> = Cell::new(0);
+}
+
+/// RAII guard keeping a single-threaded COM apartment alive on the current
+/// thread, which is what the IMAPI objects require.
+///
+/// Guards nest: only the first one on a thread calls `CoInitializeEx` and
+/// only the last one dropped calls `CoUninitialize`, so helpers can take
+/// their own guard without fighting over the apartment with their caller.
+///
+/// ```no_run
+/// # fn doc(burner: &windows::Win32::Storage::Imapi::IDiscFormat2Data,
+/// #        iso: std::fs::File) -> Result<(), imapi_utils::BurnError> {
+/// use imapi_utils::{burn, BurnOptions, ComApartment};
+///
+/// let _com = ComApartment::enter()?;
+/// burn(burner, || &iso, BurnOptions::default())?;
+/// // The apartment is torn down when `_com` goes out of scope.
+/// # Ok(())
+/// # }
+/// ```
+pub struct ComApartment {
+    // Apartments are per-thread; keep the guard on the thread it entered.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl ComApartment {
+    /// Enters (or joins) the single-threaded apartment on this thread.
+    pub fn enter() -> Result<ComApartment, BurnError> {
+        APARTMENT_DEPTH.with(|depth| {
+            if depth.get() == 0 {
+                unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED)? };
+            }
+            depth.set(depth.get() + 1);
+            Ok(ComApartment {
+                _not_send: PhantomData,
+            })
+        })
+    }
+}
+
+impl Drop for ComApartment {
+    fn drop(&mut self) {
+        APARTMENT_DEPTH.with(|depth| {
+            depth.set(depth.get() - 1);
+            if depth.get() == 0 {
+                unsafe { CoUninitialize() };
+            }
+        });
+    }
+}
+
+/// Errors with `ComNotInitialized` unless a `ComApartment` guard is live on
+/// the current thread. The entry points doing event advising call this first
+/// so the failure mode is a clear message instead of a cryptic HRESULT.
+pub(crate) fn ensure_apartment() -> Result<(), BurnError> {
+    if APARTMENT_DEPTH.with(|depth| depth.get()) > 0 {
+        Ok(())
+    } else {
+        Err(BurnError::ComNotInitialized)
+    }
+}
+
+/// A recorder reference explicitly marshaled for another thread, since COM
+/// interface pointers themselves aren't `Send`.
+///
+/// Built on `CoMarshalInterThreadInterfaceInStream`: the handle can be moved
+/// to exactly one other thread, which (with COM initialized) turns it back
+/// into a usable `IDiscRecorder2` proxy via `unmarshal_on_current_thread`.
+/// A handle that is never unmarshaled keeps a reference on the recorder
+/// until the marshal data is released with the stream.
+pub struct MarshaledRecorder {
+    stream: Option<IStream>,
+}
+
+// The marshal stream is the one COM object designed to cross threads; the
+// interface it carries is only materialized after unmarshaling.
+unsafe impl Send for MarshaledRecorder {}
+
+impl MarshaledRecorder {
+    /// Marshals `recorder` on its home thread.
+    pub fn new(recorder: &IDiscRecorder2) -> Result<MarshaledRecorder, BurnError> {
+        let stream =
+            unsafe { CoMarshalInterThreadInterfaceInStream(&IDiscRecorder2::IID, recorder)? };
+        Ok(MarshaledRecorder {
+            stream: Some(stream),
+        })
+    }
+
+    /// Turns the handle back into a recorder proxy on the calling thread,
+    /// which must have COM initialized. Consumes the handle: the marshal
+    /// data is only valid for one unmarshal.
+    pub fn unmarshal_on_current_thread(mut self) -> Result<IDiscRecorder2, BurnError> {
+        let stream = self
+            .stream
+            .take()
+            .expect("the marshal stream is present until consumed");
+        Ok(unsafe { CoGetInterfaceAndReleaseStream(&stream)? })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn marshals_across_threads() {
+        use crate::mock::MockRecorderBuilder;
+
+        let _com = ComApartment::enter().unwrap();
+        let (recorder, calls) = MockRecorderBuilder::new().build();
+        let handle = MarshaledRecorder::new(&recorder).unwrap();
+        std::thread::spawn(move || {
+            let _com = ComApartment::enter().unwrap();
+            let recorder = handle.unmarshal_on_current_thread().unwrap();
+            unsafe { recorder.EjectMedia() }.unwrap();
+        })
+        .join()
+        .unwrap();
+        assert_eq!(calls.eject_media(), 1);
+    }
+
+    #[test]
+    fn guards_nest_and_release() {
+        assert!(ensure_apartment().is_err());
+        {
+            let _outer = ComApartment::enter().unwrap();
+            let _inner = ComApartment::enter().unwrap();
+            assert!(ensure_apartment().is_ok());
+        }
+        assert!(ensure_apartment().is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//! Structured view of the MMC READ DISC INFORMATION block.
+
+use crate::error::BurnError;
+use crate::scsi::get_disc_information_raw;
+use windows::Win32::Storage::Imapi::IDiscRecorder2Ex;
+
+/// Overall disc status, from the low bits of the status byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DiscStatus {
+    /// Blank disc.
+    Empty,
+    /// An open session exists; the disc can be appended to.
+    Incomplete,
+    /// The disc is finalized.
+    Finalized,
+    /// Random-access media, or a code this build doesn't know.
+    Other(u8),
+}
+
+/// State of the last session on the disc.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SessionState {
+    Empty,
+    Incomplete,
+    Damaged,
+    Complete,
+}
+
+/// Parsed READ DISC INFORMATION block. This is what append and finalize
+/// decisions are based on.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiscInformation {
+    pub disc_status: DiscStatus,
+    pub state_of_last_session: SessionState,
+    pub erasable: bool,
+    pub number_of_sessions: u16,
+    /// First track number on the disc.
+    pub first_track: u8,
+    /// Last track number in the last session.
+    pub last_track: u16,
+    /// Disc identification, when the drive reports a valid one.
+    pub disc_id: Option<u32>,
+}
+
+impl DiscInformation {
+    /// Whether a new session can still be added.
+    pub fn is_appendable(&self) -> bool {
+        matches!(self.disc_status, DiscStatus::Empty | DiscStatus::Incomplete)
+    }
+
+    pub(crate) fn parse(data: &[u8]) -> Result<DiscInformation, BurnError> {
+        if data.len() < 12 {
+            return Err(BurnError::MalformedResponse("disc information too short"));
+        }
+        let status = data[2];
+        let disc_status = match status & 0x03 {
+            0 => DiscStatus::Empty,
+            1 => DiscStatus::Incomplete,
+            2 => DiscStatus::Finalized,
+            other => DiscStatus::Other(other),
+        };
+        let state_of_last_session = match (status >> 2) & 0x03 {
+            0 => SessionState::Empty,
+            1 => SessionState::Incomplete,
+            2 => SessionState::Damaged,
+            _ => SessionState::Complete,
+        };
+        // Session and track counts are split into LSB (bytes 4..=6) and MSB
+        // (bytes 9..=11) halves.
+        let number_of_sessions = u16::from_be_bytes([data[9], data[4]]);
+        let last_track = u16::from_be_bytes([data[11], data[6]]);
+        let disc_id = if data[7] & 0x80 != 0 && data.len() >= 16 {
+            Some(u32::from_be_bytes([data[12], data[13], data[14], data[15]]))
+        } else {
+            None
+        };
+        Ok(DiscInformation {
+            disc_status,
+            state_of_last_session,
+            erasable: status & 0x10 != 0,
+            number_of_sessions,
+            first_track: data[3],
+            last_track,
+            disc_id,
+        })
+    }
+}
+
+/// Parses a raw MMC DISC INFORMATION blob, for callers that already hold
+/// the bytes (for example from a pass-through READ DISC INFORMATION).
+pub fn parse_disc_information(data: &[u8]) -> Result<DiscInformation, BurnError> {
+    DiscInformation::parse(data)
+}
+
+/// Reads and parses the disc information of the media in `recorder`.
+pub fn disc_information(recorder: &IDiscRecorder2Ex) -> Result<DiscInformation, BurnError> {
+    DiscInformation::parse(&get_disc_information_raw(recorder)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_appendable_cdr() {
+        // Incomplete disc, incomplete last session, erasable bit clear, one
+        // session, tracks 1..=2, valid disc id.
+        let data = [
+            0x00,
+            0x20,        // length
+            0b0000_0101, // last session incomplete, disc incomplete
+            0x01,        // first track
+            0x01,        // sessions LSB
+            0x02,        // first track in last session LSB
+            0x02,        // last track LSB
+            0x80,        // DID_V
+            0x00,        // disc type
+            0x00,
+            0x00,
+            0x00, // MSB halves
+            0xde,
+            0xad,
+            0xbe,
+            0xef, // disc id
+        ];
+        let info = DiscInformation::parse(&data).unwrap();
+        assert_eq!(info.disc_status, DiscStatus::Incomplete);
+        assert_eq!(info.state_of_last_session, SessionState::Incomplete);
+        assert!(!info.erasable);
+        assert!(info.is_appendable());
+        assert_eq!(info.number_of_sessions, 1);
+        assert_eq!(info.first_track, 1);
+        assert_eq!(info.last_track, 2);
+        assert_eq!(info.disc_id, Some(0xdead_beef));
+    }
+
+    #[test]
+    fn parse_finalized_disc() {
+        let data = [
+            0x00,
+            0x20,
+            0b0000_1110,
+            0x01,
+            0x02,
+            0x03,
+            0x09,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+        ];
+        let info = DiscInformation::parse(&data).unwrap();
+        assert_eq!(info.disc_status, DiscStatus::Finalized);
+        assert_eq!(info.state_of_last_session, SessionState::Complete);
+        assert!(!info.is_appendable());
+        assert_eq!(info.disc_id, None);
+    }
+
+    #[test]
+    fn short_block_is_rejected() {
+        assert!(DiscInformation::parse(&[0u8; 4]).is_err());
+    }
+}
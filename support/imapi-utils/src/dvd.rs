@@ -0,0 +1,186 @@
+//! Guarded access to SEND DVD STRUCTURE for authoring tools.
+
+use crate::error::BurnError;
+use crate::scsi::ScsiCommand;
+use windows::Win32::Storage::Imapi::IDiscRecorder2Ex;
+
+// Structure format codes this module knows how to build.
+const FORMAT_TIMESTAMP: u8 = 0x0f;
+// Format 0xff lists the structures the drive can read or send.
+const FORMAT_CAPABILITY_LIST: u8 = 0xff;
+// "Structure is sendable" bit in a capability list entry.
+const SDS_BIT: u8 = 0x80;
+
+// Expected payload sizes for the formats with a fixed layout.
+const KNOWN_PAYLOAD_SIZES: &[(u8, usize)] = &[(FORMAT_TIMESTAMP, 16)];
+
+/// Disc timestamp written through structure format 0x0f.
+#[derive(Clone, Copy, Debug)]
+pub struct DvdTimestamp {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DvdTimestamp {
+    fn payload(&self) -> Result<Vec<u8>, BurnError> {
+        if self.year > 9999
+            || !(1..=12).contains(&self.month)
+            || !(1..=31).contains(&self.day)
+            || self.hour > 23
+            || self.minute > 59
+            || self.second > 59
+        {
+            return Err(BurnError::InvalidDvdStructure(
+                "timestamp field out of range",
+            ));
+        }
+        // Two reserved bytes followed by zero-padded ASCII digit fields.
+        let mut payload = vec![0u8; 2];
+        payload.extend_from_slice(format!("{:04}", self.year).as_bytes());
+        for field in [self.month, self.day, self.hour, self.minute, self.second] {
+            payload.extend_from_slice(format!("{:02}", field).as_bytes());
+        }
+        Ok(payload)
+    }
+}
+
+/// A DVD structure to send to the drive.
+pub enum DvdStructure {
+    /// Recording timestamp (format 0x0f).
+    Timestamp(DvdTimestamp),
+    /// Any other structure, for callers that know the raw layout. The
+    /// payload length is still validated when the format is one with a
+    /// fixed size.
+    Raw { format: u8, data: Vec<u8> },
+}
+
+impl DvdStructure {
+    fn format(&self) -> u8 {
+        match self {
+            DvdStructure::Timestamp(_) => FORMAT_TIMESTAMP,
+            DvdStructure::Raw { format, .. } => *format,
+        }
+    }
+
+    fn payload(&self) -> Result<Vec<u8>, BurnError> {
+        match self {
+            DvdStructure::Timestamp(timestamp) => timestamp.payload(),
+            DvdStructure::Raw { format, data } => {
+                if let Some((_, expected)) = KNOWN_PAYLOAD_SIZES
+                    .iter()
+                    .find(|(known, _)| known == format)
+                {
+                    if data.len() != *expected {
+                        return Err(BurnError::InvalidDvdStructure(
+                            "payload length does not match the format",
+                        ));
+                    }
+                }
+                Ok(data.clone())
+            }
+        }
+    }
+}
+
+// Format codes the drive reports as sendable, from the capability list
+// structure (format 0xff, one 4-byte entry per format).
+fn sendable_formats(recorder: &IDiscRecorder2Ex) -> Result<Vec<u8>, BurnError> {
+    let cdb: [u8; 12] = [
+        0xad, // READ DVD STRUCTURE
+        0,
+        0,
+        0,
+        0,
+        0,
+        0, // layer
+        FORMAT_CAPABILITY_LIST,
+        0x08,
+        0x00, // allocation length 2048
+        0,
+        0,
+    ];
+    let mut buffer = [0u8; 2048];
+    let fetched = ScsiCommand::new(&cdb).get_data(recorder, &mut buffer)? as usize;
+    let mut formats = Vec::new();
+    if fetched > 4 {
+        for entry in buffer[4..fetched].chunks_exact(4) {
+            if entry[1] & SDS_BIT != 0 {
+                formats.push(entry[0]);
+            }
+        }
+    }
+    Ok(formats)
+}
+
+/// Sends `structure` to the drive after validating the payload and checking
+/// the capability list, so unsupported formats fail with a clear error
+/// instead of a drive-specific sense code.
+pub fn send_dvd_structure(
+    recorder: &IDiscRecorder2Ex,
+    structure: DvdStructure,
+) -> Result<(), BurnError> {
+    let format = structure.format();
+    let payload = structure.payload()?;
+    if !sendable_formats(recorder)?.contains(&format) {
+        return Err(BurnError::Unsupported(
+            "the drive cannot write this DVD structure",
+        ));
+    }
+    unsafe {
+        recorder.SendDvdStructure(u32::from(format), payload.as_ptr(), payload.len() as u32)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn timestamp_payload_layout() {
+        let payload = DvdTimestamp {
+            year: 2024,
+            month: 3,
+            day: 7,
+            hour: 12,
+            minute: 34,
+            second: 56,
+        }
+        .payload()
+        .unwrap();
+        assert_eq!(&payload[..2], &[0, 0]);
+        assert_eq!(&payload[2..], b"20240307123456");
+    }
+
+    #[test]
+    fn out_of_range_timestamp_is_rejected() {
+        let bad = DvdTimestamp {
+            year: 2024,
+            month: 13,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+        };
+        assert!(bad.payload().is_err());
+    }
+
+    #[test]
+    fn raw_payload_length_is_checked() {
+        let short = DvdStructure::Raw {
+            format: FORMAT_TIMESTAMP,
+            data: vec![0; 4],
+        };
+        assert!(short.payload().is_err());
+
+        let unknown = DvdStructure::Raw {
+            format: 0xc0,
+            data: vec![0; 4],
+        };
+        assert!(unknown.payload().is_ok());
+    }
+}
@@ -0,0 +1,213 @@
+//! Typed wrapper around the `IDiscFormat2Erase` flow.
+
+use crate::com::{ensure_apartment, ComApartment};
+use crate::error::{BurnError, ImapiError};
+use crate::events::{EraseEventSink, EraseState, EventCookie};
+use crate::media::{media_write_mode, MediaType, WriteMode};
+use crate::util::string_to_bstr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use windows::core::{AgileReference, ComInterface};
+use windows::Win32::Foundation::VARIANT_BOOL;
+use windows::Win32::Storage::Imapi::{
+    DDiscFormat2EraseEvents, IDiscFormat2Data, IDiscFormat2Erase, IDiscRecorder2,
+    IMAPI_FORMAT2_DATA_MEDIA_STATE_ERASE_REQUIRED,
+};
+
+/// A single progress notification from the erase event sink.
+#[derive(Clone, Copy, Debug)]
+pub struct EraseProgress {
+    /// Time spent erasing so far.
+    pub elapsed: Duration,
+    /// Drive estimate of the total erase duration. `None` when the drive
+    /// reports zero, meaning it doesn't know.
+    pub estimated_total: Option<Duration>,
+}
+
+impl EraseProgress {
+    pub(crate) fn new(elapsed_seconds: i32, estimated_total_seconds: i32) -> Self {
+        EraseProgress {
+            elapsed: Duration::from_secs(elapsed_seconds.max(0) as u64),
+            estimated_total: if estimated_total_seconds > 0 {
+                Some(Duration::from_secs(estimated_total_seconds as u64))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Completion ratio in the 0..=100 range, or `None` when the drive didn't
+    /// provide a total estimate. This never divides by zero.
+    pub fn percent_done(&self) -> Option<f64> {
+        self.estimated_total
+            .map(|total| (self.elapsed.as_secs_f64() / total.as_secs_f64() * 100.0).min(100.0))
+    }
+}
+
+/// Structured confirmation returned once `EraseMedia` succeeded, since the
+/// erase events only carry progress and `EraseMedia` itself returns nothing.
+#[derive(Clone, Copy, Debug)]
+pub struct EraseReport {
+    /// Whether a full erase was performed rather than a quick one.
+    pub full: bool,
+    /// Erase duration, computed from the last progress event.
+    pub elapsed: Duration,
+    /// The kind of media that was erased.
+    pub media_type: MediaType,
+}
+
+/// Erases the media in the recorder already attached to `erase`, reporting
+/// progress through the optional callback and returning a typed completion
+/// report instead of `()`.
+pub fn erase_media(
+    erase: &IDiscFormat2Erase,
+    full: bool,
+    progress: Option<Box<dyn FnMut(EraseProgress) + Send>>,
+) -> Result<EraseReport, BurnError> {
+    erase_media_with_cancel(erase, full, progress, None)
+}
+
+// Shared implementation behind `erase_media` and `EraseSession`; the cancel
+// flag, when present, is polled from the erase event sink.
+fn erase_media_with_cancel(
+    erase: &IDiscFormat2Erase,
+    full: bool,
+    progress: Option<Box<dyn FnMut(EraseProgress) + Send>>,
+    cancel: Option<Arc<AtomicBool>>,
+) -> Result<EraseReport, BurnError> {
+    ensure_apartment()?;
+    let state = Arc::new(Mutex::new(EraseState::default()));
+    let sink: DDiscFormat2EraseEvents = EraseEventSink::new(state.clone(), progress, cancel).into();
+
+    unsafe {
+        erase.SetFullErase(VARIANT_BOOL::from(full))?;
+        // Keep the sink advised only while EraseMedia pumps events.
+        let _cookie = EventCookie::advise::<DDiscFormat2EraseEvents, _>(erase, &sink.cast()?)?;
+        erase.EraseMedia()?;
+
+        let media_type = MediaType::from(erase.CurrentPhysicalMediaType()?);
+        let elapsed_seconds = state.lock().map(|s| s.elapsed_seconds).unwrap_or(0);
+        Ok(EraseReport {
+            full,
+            elapsed: Duration::from_secs(elapsed_seconds.max(0) as u64),
+            media_type,
+        })
+    }
+}
+
+/// An erase running on its own worker thread, so the caller keeps control
+/// and can abort it.
+///
+/// Cancellation works by failing the next `Update` event, which makes
+/// `EraseMedia` give up; how quickly the drive actually stops a full erase
+/// is up to its firmware, so expect some latency after `cancel()`.
+pub struct EraseSession {
+    cancel: Arc<AtomicBool>,
+    worker: Option<std::thread::JoinHandle<Result<EraseReport, BurnError>>>,
+}
+
+impl EraseSession {
+    /// Starts erasing on a dedicated thread (with its own apartment) and
+    /// returns immediately. Progress keeps flowing through `progress`.
+    pub fn start(
+        erase: &IDiscFormat2Erase,
+        full: bool,
+        progress: Option<Box<dyn FnMut(EraseProgress) + Send>>,
+    ) -> Result<EraseSession, BurnError> {
+        let agile = AgileReference::new(erase)?;
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancel_worker = cancel.clone();
+        let worker = std::thread::spawn(move || {
+            let _com = ComApartment::enter()?;
+            let erase = agile.resolve()?;
+            erase_media_with_cancel(&erase, full, progress, Some(cancel_worker))
+        });
+        Ok(EraseSession {
+            cancel,
+            worker: Some(worker),
+        })
+    }
+
+    /// Requests cancellation; the erase aborts at the next progress event.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    /// Waits for the erase to finish, returning its report, or the abort
+    /// error when the session was cancelled.
+    pub fn wait(mut self) -> Result<EraseReport, BurnError> {
+        match self.worker.take() {
+            Some(worker) => worker
+                .join()
+                .unwrap_or(Err(BurnError::Unsupported("the erase thread died"))),
+            None => Err(BurnError::Unsupported("the erase already completed")),
+        }
+    }
+}
+
+/// Spelled-out erase depth, replacing the raw boolean of `SetFullErase`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EraseMode {
+    /// Erase just enough for the media to be rewritten.
+    Quick,
+    /// Erase the whole surface; takes much longer.
+    Full,
+}
+
+/// Safe wrapper around `IDiscFormat2Erase` for callers that only need the
+/// plain blocking flow. Errors surface as `ImapiError` so the common drive
+/// conditions are directly matchable.
+pub struct DiscEraser {
+    erase: IDiscFormat2Erase,
+}
+
+impl DiscEraser {
+    /// Wraps an already created `IDiscFormat2Erase`.
+    pub fn new(erase: IDiscFormat2Erase) -> DiscEraser {
+        DiscEraser { erase }
+    }
+
+    /// Attaches the recorder holding the media to erase.
+    pub fn set_recorder(&self, recorder: &IDiscRecorder2) -> Result<(), ImapiError> {
+        unsafe { self.erase.SetRecorder(recorder) }.map_err(ImapiError::from)
+    }
+
+    /// Sets the client name drives show while the eraser holds exclusive
+    /// access.
+    pub fn set_client_name(&self, name: &str) -> Result<(), ImapiError> {
+        unsafe { self.erase.SetClientName(&string_to_bstr(name)) }.map_err(ImapiError::from)
+    }
+
+    /// Erases the media to the requested depth.
+    pub fn erase(&self, mode: EraseMode) -> Result<(), ImapiError> {
+        unsafe {
+            self.erase
+                .SetFullErase(VARIANT_BOOL::from(mode == EraseMode::Full))
+                .and_then(|()| self.erase.EraseMedia())
+        }
+        .map_err(ImapiError::from)
+    }
+}
+
+/// Makes sure the loaded rewritable media can be written to, quick-erasing
+/// (which doubles as formatting) when the drive flags it as needing one.
+///
+/// Write-once and already-writable media are left untouched, so this is safe
+/// to call unconditionally at the start of a burn workflow. Formatting a
+/// fresh DVD-RW or BD-RE can take minutes, hence the optional progress
+/// callback forwarded to the erase.
+pub fn ensure_writable(
+    burner: &IDiscFormat2Data,
+    erase: &IDiscFormat2Erase,
+    progress: Option<Box<dyn FnMut(EraseProgress) + Send>>,
+) -> Result<(), BurnError> {
+    if media_write_mode(burner)? != WriteMode::Overwritable {
+        return Ok(());
+    }
+    let status = unsafe { burner.CurrentMediaStatus()? };
+    if status.0 & IMAPI_FORMAT2_DATA_MEDIA_STATE_ERASE_REQUIRED.0 == 0 {
+        return Ok(());
+    }
+    erase_media(erase, false, progress).map(|_| ())
+}
@@ -0,0 +1,115 @@
+//! Error type shared by the IMAPI wrappers.
+
+use crate::image::NameError;
+use crate::sense::SenseData;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum BurnError {
+    /// An unclassified COM failure, keeping the original `HRESULT`.
+    #[error("COM error: {0}")]
+    Com(#[from] windows::core::Error),
+    /// An entry point needing COM was called without a live apartment.
+    #[error("COM is not initialized on this thread; enter a ComApartment first")]
+    ComNotInitialized,
+    /// A local I/O failure while feeding or comparing source data.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// `CreateResultImage` was called without a usable capacity configured.
+    #[error("image capacity was not configured")]
+    CapacityNotSet,
+    /// Appending was requested on a disc that was finalized.
+    #[error("the disc is finalized and cannot be appended to")]
+    DiscFinalized,
+    /// The media was swapped between the support check and the write.
+    #[error("the media changed since it was checked")]
+    MediaChanged,
+    /// An item name was rejected by the file system image.
+    #[error("invalid item name: {0}")]
+    InvalidName(#[from] NameError),
+    /// An audio track's PCM payload failed validation before burning.
+    #[error("invalid audio track: {0}")]
+    InvalidAudioTrack(&'static str),
+    /// An invalid El Torito boot configuration was requested.
+    #[error("invalid boot options: {0}")]
+    InvalidBootOptions(&'static str),
+    /// A DVD structure payload failed validation before being sent.
+    #[error("invalid DVD structure: {0}")]
+    InvalidDvdStructure(&'static str),
+    /// The drive answered a pass-through command with data we can't parse.
+    #[error("malformed device response: {0}")]
+    MalformedResponse(&'static str),
+    /// A symlink cycle was detected while staging a folder.
+    #[error("symlink cycle detected at {0:?}")]
+    SymlinkCycle(std::path::PathBuf),
+    /// A symlink was encountered while the policy forbids them.
+    #[error("symlink rejected at {0:?}")]
+    SymlinkRejected(std::path::PathBuf),
+    /// The requested operation is not meaningful for the loaded media.
+    #[error("unsupported: {0}")]
+    Unsupported(&'static str),
+    /// A simulated burn was requested but the drive doesn't support test
+    /// writes.
+    #[error("the drive does not support test writes")]
+    SimulationNotSupported,
+    /// The drive reported a power calibration area error. These are often
+    /// transient: retrying the burn at a lower write speed usually succeeds.
+    #[error("power calibration error (sense: {0:?})")]
+    PowerCalibration(Option<SenseData>),
+    /// The drive ran out of buffered data while writing (buffer underrun).
+    #[error("buffer underrun (sense: {0:?})")]
+    BufferUnderrun(Option<SenseData>),
+    /// A drive call did not complete within the allotted time.
+    #[error("the drive did not respond within the timeout")]
+    Timeout,
+    /// Read-back verification found disc content differing from the source.
+    #[error("read-back verification failed at LBA {0}")]
+    VerificationFailed(u32),
+}
+
+/// Classification of the well-known IMAPI facility HRESULTs, so callers can
+/// match on conditions instead of memorizing numeric codes.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum ImapiError {
+    #[error("no media in the drive")]
+    MediaNotPresent,
+    #[error("the media is write protected")]
+    MediaWriteProtected,
+    #[error("the recorder is locked by another client")]
+    RecorderInUse,
+    #[error("the device stopped responding")]
+    DeviceNotAccessible,
+    /// Any other code, preserved verbatim.
+    #[error("IMAPI error {0:?}")]
+    Other(windows::core::HRESULT),
+}
+
+impl From<windows::core::Error> for ImapiError {
+    fn from(error: windows::core::Error) -> Self {
+        use windows::Win32::System::AddressBook::{
+            E_IMAPI_RECORDER_COMMAND_TIMEOUT, E_IMAPI_RECORDER_LOCKED, E_IMAPI_RECORDER_MEDIA_BUSY,
+            E_IMAPI_RECORDER_MEDIA_NO_MEDIA, E_IMAPI_RECORDER_MEDIA_WRITE_PROTECTED,
+        };
+        match error.code() {
+            code if code == E_IMAPI_RECORDER_MEDIA_NO_MEDIA => ImapiError::MediaNotPresent,
+            code if code == E_IMAPI_RECORDER_MEDIA_WRITE_PROTECTED => {
+                ImapiError::MediaWriteProtected
+            }
+            code if code == E_IMAPI_RECORDER_LOCKED || code == E_IMAPI_RECORDER_MEDIA_BUSY => {
+                ImapiError::RecorderInUse
+            }
+            code if code == E_IMAPI_RECORDER_COMMAND_TIMEOUT => ImapiError::DeviceNotAccessible,
+            code => ImapiError::Other(code),
+        }
+    }
+}
+
+impl BurnError {
+    /// The IMAPI classification of this error, when it wraps a COM failure.
+    pub fn imapi(&self) -> Option<ImapiError> {
+        match self {
+            BurnError::Com(error) => Some(ImapiError::from(error.clone())),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,483 @@
+//! Connection point plumbing for the IMAPI event dispinterfaces.
+//!
+//! IMAPI delivers progress through pure dispinterfaces, so the sinks here
+//! implement `IDispatch` and decode the `Invoke` parameters themselves in
+//! addition to exposing the strongly typed `Update` entry point.
+
+use crate::erase::EraseProgress;
+use crate::progress::BurnProgress;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use windows::core::{implement, ComInterface, Error, IUnknown, Result, GUID, PCWSTR};
+use windows::Win32::Foundation::{
+    DISP_E_BADPARAMCOUNT, DISP_E_MEMBERNOTFOUND, E_ABORT, E_NOTIMPL, E_POINTER,
+};
+use windows::Win32::Storage::Imapi::{
+    DDiscFormat2DataEvents, DDiscFormat2DataEvents_Impl, DDiscFormat2EraseEvents,
+    DDiscFormat2EraseEvents_Impl, IDiscFormat2DataEventArgs,
+};
+use windows::Win32::System::Com::{
+    IConnectionPoint, IConnectionPointContainer, IDispatch, IDispatch_Impl, ITypeInfo,
+    DISPATCH_FLAGS, DISPPARAMS, EXCEPINFO,
+};
+use windows::Win32::System::Variant::{VARIANT, VT_BSTR, VT_DISPATCH, VT_I4, VT_UI4};
+
+// All the IMAPI `Update` event methods share this DISPID (imapi2.idl).
+pub(crate) const DISPID_IMAPI_EVENTS_UPDATE: i32 = 0x200;
+// `DDiscMaster2Events` notification DISPIDs (imapi2.idl).
+pub(crate) const DISPID_DDISCMASTER2EVENTS_DEVICE_ADDED: i32 = 0x100;
+pub(crate) const DISPID_DDISCMASTER2EVENTS_DEVICE_REMOVED: i32 = 0x101;
+
+// Extracts a 32 bit integer from a VARIANT, tolerating the unsigned spelling
+// some providers use.
+pub(crate) fn variant_to_i32(value: &VARIANT) -> Option<i32> {
+    unsafe {
+        let inner = &value.Anonymous.Anonymous;
+        match inner.vt {
+            VT_I4 | VT_UI4 => Some(inner.Anonymous.lVal),
+            _ => None,
+        }
+    }
+}
+
+// Extracts a BSTR argument from a VARIANT.
+pub(crate) fn variant_to_bstr(value: &VARIANT) -> Option<windows::core::BSTR> {
+    unsafe {
+        let inner = &value.Anonymous.Anonymous;
+        if inner.vt == VT_BSTR {
+            Some((*inner.Anonymous.bstrVal).clone())
+        } else {
+            None
+        }
+    }
+}
+
+// Extracts an `IDispatch` argument from a VARIANT.
+pub(crate) fn variant_to_dispatch(value: &VARIANT) -> Option<IDispatch> {
+    unsafe {
+        let inner = &value.Anonymous.Anonymous;
+        if inner.vt == VT_DISPATCH {
+            (*inner.Anonymous.pdispVal).clone()
+        } else {
+            None
+        }
+    }
+}
+
+/// RAII wrapper keeping an event sink advised on one of the outgoing
+/// interfaces of `source` until dropped.
+pub(crate) struct EventCookie {
+    point: IConnectionPoint,
+    cookie: u32,
+}
+
+impl EventCookie {
+    /// Connects `sink` to the `E` outgoing interface of `source`.
+    pub(crate) fn advise<E: ComInterface, S: ComInterface>(
+        source: &S,
+        sink: &IUnknown,
+    ) -> Result<Self> {
+        unsafe {
+            let container: IConnectionPointContainer = source.cast()?;
+            let point = container.FindConnectionPoint(&E::IID)?;
+            let cookie = point.Advise(sink)?;
+            Ok(EventCookie { point, cookie })
+        }
+    }
+}
+
+impl Drop for EventCookie {
+    fn drop(&mut self) {
+        let _ = unsafe { self.point.Unadvise(self.cookie) };
+    }
+}
+
+/// Last progress values reported by `DDiscFormat2EraseEvents`.
+#[derive(Default)]
+pub(crate) struct EraseState {
+    pub elapsed_seconds: i32,
+    pub estimated_total_seconds: i32,
+}
+
+/// Sink attached to `IDiscFormat2Erase` while `EraseMedia` runs.
+#[implement(DDiscFormat2EraseEvents)]
+pub(crate) struct EraseEventSink {
+    state: Arc<Mutex<EraseState>>,
+    callback: Mutex<Option<Box<dyn FnMut(EraseProgress) + Send>>>,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl EraseEventSink {
+    pub(crate) fn new(
+        state: Arc<Mutex<EraseState>>,
+        callback: Option<Box<dyn FnMut(EraseProgress) + Send>>,
+        cancel: Option<Arc<AtomicBool>>,
+    ) -> Self {
+        EraseEventSink {
+            state,
+            callback: Mutex::new(callback),
+            cancel,
+        }
+    }
+}
+
+impl DDiscFormat2EraseEvents_Impl for EraseEventSink {
+    fn Update(
+        &self,
+        _object: Option<&IDispatch>,
+        elapsedseconds: i32,
+        estimatedtotalseconds: i32,
+    ) -> Result<()> {
+        if let Ok(mut state) = self.state.lock() {
+            state.elapsed_seconds = elapsedseconds;
+            state.estimated_total_seconds = estimatedtotalseconds;
+        }
+        if let Ok(mut callback) = self.callback.lock() {
+            if let Some(callback) = callback.as_mut() {
+                callback(EraseProgress::new(elapsedseconds, estimatedtotalseconds));
+            }
+        }
+        // Failing the event callback is the only way to abort EraseMedia.
+        if let Some(cancel) = &self.cancel {
+            if cancel.load(Ordering::SeqCst) {
+                return Err(E_ABORT.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl IDispatch_Impl for EraseEventSink {
+    fn GetTypeInfoCount(&self) -> Result<u32> {
+        Ok(0)
+    }
+
+    fn GetTypeInfo(&self, _itinfo: u32, _lcid: u32) -> Result<ITypeInfo> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn GetIDsOfNames(
+        &self,
+        _riid: *const GUID,
+        _rgsznames: *const PCWSTR,
+        _cnames: u32,
+        _lcid: u32,
+        _rgdispid: *mut i32,
+    ) -> Result<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn Invoke(
+        &self,
+        dispidmember: i32,
+        _riid: *const GUID,
+        _lcid: u32,
+        _wflags: DISPATCH_FLAGS,
+        pdispparams: *const DISPPARAMS,
+        _pvarresult: *mut VARIANT,
+        _pexcepinfo: *mut EXCEPINFO,
+        _puargerr: *mut u32,
+    ) -> Result<()> {
+        if dispidmember != DISPID_IMAPI_EVENTS_UPDATE {
+            return Err(DISP_E_MEMBERNOTFOUND.into());
+        }
+        let params = unsafe { pdispparams.as_ref() }.ok_or_else(|| Error::from(E_POINTER))?;
+        if params.cArgs != 3 {
+            return Err(DISP_E_BADPARAMCOUNT.into());
+        }
+        // Dispatch arguments are stored right to left.
+        let args = unsafe { std::slice::from_raw_parts(params.rgvarg, params.cArgs as usize) };
+        let estimated_total = variant_to_i32(&args[0]).unwrap_or(0);
+        let elapsed = variant_to_i32(&args[1]).unwrap_or(0);
+        self.Update(None, elapsed, estimated_total)
+    }
+}
+
+/// Sink attached to `IDiscFormat2Data` while `Write` runs.
+#[implement(DDiscFormat2DataEvents)]
+pub(crate) struct DataEventSink {
+    callback: Mutex<Box<dyn FnMut(BurnProgress) + Send>>,
+    #[cfg(feature = "tracing")]
+    last_phase: Mutex<Option<crate::progress::BurnPhase>>,
+}
+
+impl DataEventSink {
+    pub(crate) fn new(callback: Box<dyn FnMut(BurnProgress) + Send>) -> Self {
+        DataEventSink {
+            callback: Mutex::new(callback),
+            #[cfg(feature = "tracing")]
+            last_phase: Mutex::new(None),
+        }
+    }
+
+    // Emits a tracing event whenever the writer moves to a new action,
+    // carrying the client name so concurrent burns stay distinguishable.
+    #[cfg(feature = "tracing")]
+    fn trace_phase_change(&self, object: Option<&IDispatch>, snapshot: &BurnProgress) {
+        let mut last = match self.last_phase.lock() {
+            Ok(last) => last,
+            Err(_) => return,
+        };
+        if *last == Some(snapshot.phase) {
+            return;
+        }
+        *last = Some(snapshot.phase);
+        let client = object
+            .and_then(|object| {
+                object
+                    .cast::<windows::Win32::Storage::Imapi::IDiscFormat2Data>()
+                    .ok()
+            })
+            .and_then(|format| unsafe { format.ClientName() }.ok())
+            .map(|name| crate::util::bstr_to_string(&name))
+            .unwrap_or_default();
+        tracing::info!(
+            client = %client,
+            action = ?snapshot.phase,
+            elapsed_secs = snapshot.elapsed.as_secs(),
+            remaining_secs = snapshot.remaining.map(|remaining| remaining.as_secs()),
+            "burn action changed"
+        );
+    }
+}
+
+impl DDiscFormat2DataEvents_Impl for DataEventSink {
+    fn Update(&self, object: Option<&IDispatch>, progress: Option<&IDispatch>) -> Result<()> {
+        #[cfg(not(feature = "tracing"))]
+        let _ = object;
+        let args: IDiscFormat2DataEventArgs = match progress.and_then(|p| p.cast().ok()) {
+            Some(args) => args,
+            None => return Ok(()),
+        };
+        // A failure to read the event args shouldn't abort the burn.
+        if let Ok(snapshot) = BurnProgress::from_event_args(&args) {
+            #[cfg(feature = "tracing")]
+            self.trace_phase_change(object, &snapshot);
+            if let Ok(mut callback) = self.callback.lock() {
+                callback(snapshot);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl IDispatch_Impl for DataEventSink {
+    fn GetTypeInfoCount(&self) -> Result<u32> {
+        Ok(0)
+    }
+
+    fn GetTypeInfo(&self, _itinfo: u32, _lcid: u32) -> Result<ITypeInfo> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn GetIDsOfNames(
+        &self,
+        _riid: *const GUID,
+        _rgsznames: *const PCWSTR,
+        _cnames: u32,
+        _lcid: u32,
+        _rgdispid: *mut i32,
+    ) -> Result<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn Invoke(
+        &self,
+        dispidmember: i32,
+        _riid: *const GUID,
+        _lcid: u32,
+        _wflags: DISPATCH_FLAGS,
+        pdispparams: *const DISPPARAMS,
+        _pvarresult: *mut VARIANT,
+        _pexcepinfo: *mut EXCEPINFO,
+        _puargerr: *mut u32,
+    ) -> Result<()> {
+        if dispidmember != DISPID_IMAPI_EVENTS_UPDATE {
+            return Err(DISP_E_MEMBERNOTFOUND.into());
+        }
+        let params = unsafe { pdispparams.as_ref() }.ok_or_else(|| Error::from(E_POINTER))?;
+        if params.cArgs != 2 {
+            return Err(DISP_E_BADPARAMCOUNT.into());
+        }
+        // Dispatch arguments are stored right to left.
+        let args = unsafe { std::slice::from_raw_parts(params.rgvarg, params.cArgs as usize) };
+        let progress = variant_to_dispatch(&args[0]);
+        self.Update(None, progress.as_ref())
+    }
+}
+
+// Decides which progress updates get through a throttled sink: phase
+// changes always pass, everything else at most once per interval.
+pub(crate) struct ProgressThrottle {
+    interval: std::time::Duration,
+    last_phase: Option<crate::progress::BurnPhase>,
+    last_emit: Option<std::time::Instant>,
+}
+
+impl ProgressThrottle {
+    pub(crate) fn new(interval: std::time::Duration) -> ProgressThrottle {
+        ProgressThrottle {
+            interval,
+            last_phase: None,
+            last_emit: None,
+        }
+    }
+
+    pub(crate) fn should_forward(
+        &mut self,
+        progress: &BurnProgress,
+        now: std::time::Instant,
+    ) -> bool {
+        let phase_changed = self.last_phase != Some(progress.phase);
+        let due = match self.last_emit {
+            Some(last) => now.duration_since(last) >= self.interval,
+            None => true,
+        };
+        if phase_changed || due {
+            self.last_phase = Some(progress.phase);
+            self.last_emit = Some(now);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Keeps a `ProgressSink` connected to a writer until dropped.
+pub struct ProgressConnection {
+    _cookie: EventCookie,
+}
+
+/// A live event subscription: the sink stays advised until this is dropped,
+/// at which point `Unadvise` runs automatically.
+pub struct EventConnection {
+    _cookie: EventCookie,
+}
+
+/// Connects `sink` to the `E` outgoing dispinterface of `source` in one
+/// call: finds the `IConnectionPointContainer`, locates the connection
+/// point by `E::IID` and advises the sink.
+///
+/// `E` is the source dispinterface the sink implements, for example
+/// `DDiscFormat2DataEvents`.
+pub fn advise_events<E: ComInterface>(
+    source: &IDispatch,
+    sink: &IUnknown,
+) -> Result<EventConnection> {
+    Ok(EventConnection {
+        _cookie: EventCookie::advise::<E, _>(source, sink)?,
+    })
+}
+
+/// Ready-made `DDiscFormat2DataEvents` sink forwarding every `Update` as a
+/// `BurnProgress` into an `mpsc` channel, so receiving burn progress needs
+/// no hand-written dispinterface implementation.
+///
+/// Typical use: create the sink, `attach` it to the writer, run `Write` on
+/// another thread and loop over the receiver until it disconnects.
+pub struct ProgressSink {
+    sink: DDiscFormat2DataEvents,
+}
+
+impl ProgressSink {
+    pub fn new(sender: std::sync::mpsc::Sender<BurnProgress>) -> ProgressSink {
+        let sink: DDiscFormat2DataEvents = DataEventSink::new(Box::new(move |progress| {
+            let _ = sender.send(progress);
+        }))
+        .into();
+        ProgressSink { sink }
+    }
+
+    /// Like `new`, forwarding at most one update per `throttle` interval.
+    /// Phase transitions (including the finalization step) always go
+    /// through immediately, so only the repetitive mid-write updates are
+    /// coalesced.
+    pub fn with_throttle(
+        sender: std::sync::mpsc::Sender<BurnProgress>,
+        throttle: std::time::Duration,
+    ) -> ProgressSink {
+        let mut gate = ProgressThrottle::new(throttle);
+        let sink: DDiscFormat2DataEvents = DataEventSink::new(Box::new(move |progress| {
+            if gate.should_forward(&progress, std::time::Instant::now()) {
+                let _ = sender.send(progress);
+            }
+        }))
+        .into();
+        ProgressSink { sink }
+    }
+
+    /// Connects the sink to the outgoing interface of `burner`; events flow
+    /// until the returned connection is dropped.
+    pub fn attach(
+        &self,
+        burner: &windows::Win32::Storage::Imapi::IDiscFormat2Data,
+    ) -> Result<ProgressConnection> {
+        let cookie = EventCookie::advise::<DDiscFormat2DataEvents, _>(burner, &self.sink.cast()?)?;
+        Ok(ProgressConnection { _cookie: cookie })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::progress::BurnPhase;
+    use std::time::{Duration, Instant};
+
+    fn progress(phase: BurnPhase, written: i32) -> BurnProgress {
+        BurnProgress {
+            phase,
+            elapsed: Duration::from_secs(1),
+            remaining: None,
+            estimated_total: None,
+            start_lba: 0,
+            sector_count: 1000,
+            last_written_lba: written,
+            last_read_lba: 0,
+            write_percent: None,
+            verifying_percent: None,
+        }
+    }
+
+    #[test]
+    fn rapid_updates_are_coalesced() {
+        let mut gate = ProgressThrottle::new(Duration::from_secs(1));
+        let start = Instant::now();
+
+        // The first update always goes through.
+        assert!(gate.should_forward(&progress(BurnPhase::Writing, 0), start));
+        // A burst within the interval is suppressed.
+        for written in 1..50 {
+            assert!(!gate.should_forward(
+                &progress(BurnPhase::Writing, written),
+                start + Duration::from_millis(u64::from(written as u32) * 10),
+            ));
+        }
+        // Once the interval elapsed the next update passes again.
+        assert!(gate.should_forward(
+            &progress(BurnPhase::Writing, 50),
+            start + Duration::from_secs(1),
+        ));
+    }
+
+    #[test]
+    fn phase_changes_bypass_the_throttle() {
+        let mut gate = ProgressThrottle::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(gate.should_forward(&progress(BurnPhase::Writing, 0), start));
+        assert!(!gate.should_forward(
+            &progress(BurnPhase::Writing, 10),
+            start + Duration::from_millis(1),
+        ));
+        // Moving to finalization mid-interval is forwarded immediately.
+        assert!(gate.should_forward(
+            &progress(BurnPhase::Finalizing, 1000),
+            start + Duration::from_millis(2),
+        ));
+        // And further updates within the new phase are throttled again.
+        assert!(!gate.should_forward(
+            &progress(BurnPhase::Finalizing, 1000),
+            start + Duration::from_millis(3),
+        ));
+    }
+}
@@ -0,0 +1,35 @@
+//! Factories for the IMAPI coclasses, so callers never touch CLSIDs.
+
+use crate::error::BurnError;
+use windows::Win32::Storage::Imapi::{
+    IDiscFormat2Data, IDiscFormat2Erase, IDiscMaster2, IDiscRecorder2, IFileSystemImage,
+    MsftDiscFormat2Data, MsftDiscFormat2Erase, MsftDiscMaster2, MsftDiscRecorder2,
+    MsftFileSystemImage,
+};
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+
+/// Creates the disc master enumerating the optical drives.
+pub fn new_disc_master2() -> Result<IDiscMaster2, BurnError> {
+    Ok(unsafe { CoCreateInstance(&MsftDiscMaster2, None, CLSCTX_INPROC_SERVER)? })
+}
+
+/// Creates a data writer.
+pub fn new_format2_data() -> Result<IDiscFormat2Data, BurnError> {
+    Ok(unsafe { CoCreateInstance(&MsftDiscFormat2Data, None, CLSCTX_INPROC_SERVER)? })
+}
+
+/// Creates an eraser.
+pub fn new_format2_erase() -> Result<IDiscFormat2Erase, BurnError> {
+    Ok(unsafe { CoCreateInstance(&MsftDiscFormat2Erase, None, CLSCTX_INPROC_SERVER)? })
+}
+
+/// Creates an unattached recorder; initialize it with a unique id from the
+/// disc master before use.
+pub fn new_disc_recorder2() -> Result<IDiscRecorder2, BurnError> {
+    Ok(unsafe { CoCreateInstance(&MsftDiscRecorder2, None, CLSCTX_INPROC_SERVER)? })
+}
+
+/// Creates an empty file system image.
+pub fn new_file_system_image() -> Result<IFileSystemImage, BurnError> {
+    Ok(unsafe { CoCreateInstance(&MsftFileSystemImage, None, CLSCTX_INPROC_SERVER)? })
+}
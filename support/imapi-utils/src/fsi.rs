@@ -0,0 +1,191 @@
+//! Enumeration of the items staged in (or imported into) a file system
+//! image.
+
+use crate::error::BurnError;
+use crate::util::{bstr_to_string, string_to_bstr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use windows::core::ComInterface;
+use windows::Win32::Storage::Imapi::{
+    FsiItemDirectory, FsiItemFile, IEnumFsiItems, IFileSystemImage, IFsiDirectoryItem,
+    IFsiFileItem, IFsiItem,
+};
+
+// Days between the OLE automation epoch (1899-12-30) and the Unix epoch.
+const OLE_EPOCH_TO_UNIX_DAYS: f64 = 25_569.0;
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+// Converts an OLE automation DATE to a SystemTime. Items that don't carry a
+// timestamp report 0.0, which maps to None.
+pub(crate) fn date_to_system_time(date: f64) -> Option<SystemTime> {
+    if date == 0.0 || !date.is_finite() {
+        return None;
+    }
+    // The integer part counts days, the fractional part the portion of the
+    // day. For dates before the OLE epoch the fraction is negative but still
+    // means "into the day", hence the abs().
+    let unix_seconds = (date.trunc() - OLE_EPOCH_TO_UNIX_DAYS) * SECONDS_PER_DAY
+        + date.fract().abs() * SECONDS_PER_DAY;
+    if unix_seconds >= 0.0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs_f64(unix_seconds))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs_f64(-unix_seconds))
+    }
+}
+
+/// One entry discovered while walking an image tree, carrying the attributes
+/// needed to faithfully re-stage an imported tree into a new session.
+#[derive(Clone, Debug)]
+pub struct FsiEntry {
+    pub name: String,
+    pub full_path: String,
+    pub is_directory: bool,
+    /// Payload size in bytes; zero for directories.
+    pub data_size: i64,
+    pub created: Option<SystemTime>,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    pub hidden: bool,
+}
+
+impl FsiEntry {
+    fn from_item(item: &IFsiItem) -> Result<FsiEntry, BurnError> {
+        unsafe {
+            let file: Option<IFsiFileItem> = item.cast().ok();
+            Ok(FsiEntry {
+                name: bstr_to_string(&item.Name()?),
+                full_path: bstr_to_string(&item.FullPath()?),
+                is_directory: file.is_none(),
+                data_size: match &file {
+                    Some(file) => file.DataSize()?,
+                    None => 0,
+                },
+                created: date_to_system_time(item.CreationTime()?),
+                modified: date_to_system_time(item.LastModifiedTime()?),
+                accessed: date_to_system_time(item.LastAccessedTime()?),
+                hidden: item.IsHidden()?.as_bool(),
+            })
+        }
+    }
+}
+
+/// Iterator over the direct children of a staged directory item. Reference
+/// counting is handled by the smart pointers; nothing needs a manual
+/// release.
+pub struct FsiItemsIter {
+    inner: IEnumFsiItems,
+}
+
+impl Iterator for FsiItemsIter {
+    type Item = Result<IFsiItem, BurnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut item: Option<IFsiItem> = None;
+        let mut fetched = 0u32;
+        if let Err(err) = unsafe { self.inner.Next(1, &mut item, &mut fetched).ok() } {
+            return Some(Err(err.into()));
+        }
+        if fetched == 0 {
+            return None;
+        }
+        item.map(Ok)
+    }
+}
+
+/// The direct children of `directory`. Every call starts a fresh
+/// enumerator, so iterating twice re-enumerates from the beginning.
+pub fn children(directory: &IFsiDirectoryItem) -> Result<FsiItemsIter, BurnError> {
+    Ok(FsiItemsIter {
+        inner: unsafe { directory.EnumFsiItems()? },
+    })
+}
+
+/// Walks `root` depth first, handing every entry to `visitor`.
+pub fn walk<F: FnMut(&FsiEntry)>(
+    root: &IFsiDirectoryItem,
+    visitor: &mut F,
+) -> Result<(), BurnError> {
+    for item in children(root)? {
+        let item = item?;
+        visitor(&FsiEntry::from_item(&item)?);
+        if let Ok(directory) = item.cast::<IFsiDirectoryItem>() {
+            walk(&directory, visitor)?;
+        }
+    }
+    Ok(())
+}
+
+/// The kind of an item found in a staged image, the friendly spelling of
+/// the present `FsiItemType` values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FsiEntryKind {
+    File,
+    Directory,
+}
+
+// Image paths use backslashes; accept the forward slashes callers coming
+// from Unix-style path handling will inevitably pass.
+fn normalize_image_path(path: &str) -> String {
+    path.replace('/', "\\")
+}
+
+/// Whether `path` is staged in `image`, and as what: `None` when absent,
+/// the entry kind otherwise. Forward and backslash separators are both
+/// accepted. This replaces matching on the raw `FsiItemType` at call sites.
+pub fn exists(image: &IFileSystemImage, path: &str) -> Result<Option<FsiEntryKind>, BurnError> {
+    let kind = unsafe { image.Exists(&string_to_bstr(&normalize_image_path(path)))? };
+    Ok(match kind {
+        FsiItemFile => Some(FsiEntryKind::File),
+        FsiItemDirectory => Some(FsiEntryKind::Directory),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ole_date_conversion() {
+        // The OLE value for the Unix epoch itself.
+        assert_eq!(date_to_system_time(25_569.0), Some(UNIX_EPOCH));
+        // Half a day later.
+        assert_eq!(
+            date_to_system_time(25_569.5),
+            Some(UNIX_EPOCH + Duration::from_secs(43_200))
+        );
+        // Missing timestamps stay None.
+        assert_eq!(date_to_system_time(0.0), None);
+    }
+
+    #[test]
+    fn separators_are_normalized() {
+        assert_eq!(normalize_image_path("/boot/grub"), "\\boot\\grub");
+        assert_eq!(
+            normalize_image_path("\\already\\native"),
+            "\\already\\native"
+        );
+        assert_eq!(
+            normalize_image_path("mixed/and\\matched"),
+            "mixed\\and\\matched"
+        );
+    }
+
+    #[test]
+    fn exists_reports_staged_entries() {
+        let _com = crate::com::ComApartment::enter().unwrap();
+        let image = crate::factory::new_file_system_image().unwrap();
+        let root = unsafe { image.Root() }.unwrap();
+        unsafe { root.AddDirectory(&string_to_bstr("staged")) }.unwrap();
+
+        assert_eq!(
+            exists(&image, "\\staged").unwrap(),
+            Some(FsiEntryKind::Directory)
+        );
+        // Forward slashes reach the same entry.
+        assert_eq!(
+            exists(&image, "/staged").unwrap(),
+            Some(FsiEntryKind::Directory)
+        );
+        assert_eq!(exists(&image, "\\missing").unwrap(), None);
+    }
+}
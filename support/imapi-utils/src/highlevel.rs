@@ -0,0 +1,322 @@
+//! High-level wrappers hiding the COM surface entirely.
+
+use crate::com::ComApartment;
+use crate::error::BurnError;
+use crate::factory::{new_disc_master2, new_disc_recorder2};
+use crate::media::MediaType;
+use crate::recorder::suppress_mcn;
+use crate::safearray::read_safearray_bstr;
+use crate::sense::BurnFailure;
+use crate::speed::{one_x_sectors_per_second, request_write_speed, supported_write_speeds};
+use crate::stream::memory_stream;
+use crate::util::{bstr_to_string, string_to_bstr};
+use std::future::Future;
+use std::io::Read;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
+use windows::core::{AgileReference, ComInterface};
+use windows::Win32::Foundation::VARIANT_BOOL;
+use windows::Win32::Storage::Imapi::{
+    IDiscFormat2Data, IDiscMaster, IDiscMaster2, IDiscRecorder, IDiscRecorder2, IDiscRecorder2Ex,
+    IEnumDiscRecorders,
+};
+
+/// Safe wrapper around an acquired `IDiscFormat2Data`, for callers that
+/// want to burn without touching `BSTR`, variant booleans or raw streams.
+pub struct DiscBurner {
+    format: IDiscFormat2Data,
+}
+
+impl DiscBurner {
+    /// Wraps an already created `IDiscFormat2Data`.
+    pub fn new(format: IDiscFormat2Data) -> DiscBurner {
+        DiscBurner { format }
+    }
+
+    /// Attaches the recorder to burn to.
+    pub fn set_recorder(&self, recorder: &IDiscRecorder2) -> Result<(), BurnError> {
+        unsafe { self.format.SetRecorder(recorder)? };
+        Ok(())
+    }
+
+    /// Sets the client name drives show while the writer holds exclusive
+    /// access.
+    pub fn set_client_name(&self, name: &str) -> Result<(), BurnError> {
+        unsafe { self.format.SetClientName(&string_to_bstr(name))? };
+        Ok(())
+    }
+
+    /// The wrapped writer, for features not covered by this wrapper.
+    pub fn format(&self) -> &IDiscFormat2Data {
+        &self.format
+    }
+
+    /// Requests a write speed as the classic "X" factor for the loaded
+    /// media (4.0 on a CD-R means 300 sectors per second). The converted
+    /// rate is matched against the drive's supported speeds with a small
+    /// tolerance for the fractional DVD/BD factors; an unsupported speed is
+    /// an error rather than a silent fallback.
+    pub fn set_write_speed_x(&self, factor: f32) -> Result<(), BurnError> {
+        let media = MediaType::from(unsafe { self.format.CurrentPhysicalMediaType()? });
+        let base = one_x_sectors_per_second(media).ok_or(BurnError::Unsupported(
+            "the loaded media type has no defined 1x rate",
+        ))?;
+        let requested = f64::from(factor) * f64::from(base);
+        let speed = supported_write_speeds(self.format())?
+            .into_iter()
+            .find(|speed| (f64::from(*speed) - requested).abs() <= requested * 0.02)
+            .ok_or(BurnError::Unsupported(
+                "the drive does not support the requested write speed",
+            ))?;
+        unsafe {
+            self.format
+                .SetWriteSpeed(speed, VARIANT_BOOL::from(false))?
+        };
+        Ok(())
+    }
+
+    /// Requests a raw sectors-per-second write speed, validated against the
+    /// drive's supported speeds.
+    pub fn set_write_speed_sectors(&self, sectors_per_second: i32) -> Result<(), BurnError> {
+        request_write_speed(&self.format, sectors_per_second)
+    }
+
+    /// Reads `source` to its end and burns the content, translating a
+    /// failing write into the classified crate error (with drive sense data
+    /// when it can be captured).
+    pub fn write_stream(&self, mut source: impl Read) -> Result<(), BurnError> {
+        let mut bytes = Vec::new();
+        source.read_to_end(&mut bytes)?;
+        let stream = memory_stream(&bytes)?;
+        match unsafe { self.format.Write(&stream) } {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                let recorder: Option<IDiscRecorder2Ex> = unsafe { self.format.Recorder() }
+                    .ok()
+                    .and_then(|r| r.cast().ok());
+                Err(BurnFailure::capture(err, recorder.as_ref()).into_error())
+            }
+        }
+    }
+
+    /// Like `write_stream`, additionally ejecting the media once the write
+    /// succeeded. A failed write never ejects, so the media stays in place
+    /// for inspection; a failed eject is logged but doesn't fail the burn.
+    pub fn write_image(&self, source: impl Read, eject_after: bool) -> Result<(), BurnError> {
+        self.write_stream(source)?;
+        if eject_after {
+            if let Err(err) = unsafe { self.format.Recorder().and_then(|r| r.EjectMedia()) } {
+                log::warn!("burn succeeded but ejecting the media failed: {}", err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `write_image`, with media-change notifications suppressed for
+    /// the duration of the burn so autorun and explorer prompts don't fire
+    /// on the half-written disc. Notifications resume when the burn ends,
+    /// whatever its outcome.
+    pub fn write_image_suppressing_mcn(
+        &self,
+        source: impl Read,
+        eject_after: bool,
+    ) -> Result<(), BurnError> {
+        let recorder = unsafe { self.format.Recorder()? };
+        let _quiet = suppress_mcn(&recorder)?;
+        self.write_image(source, eject_after)
+    }
+}
+
+/// Iterator over the recorders of a legacy `IDiscMaster`, fetching one
+/// recorder per `Next` call so consumers get a plain `for` loop instead of
+/// count-and-fetched bookkeeping.
+pub struct RecordersIter {
+    inner: IEnumDiscRecorders,
+}
+
+impl RecordersIter {
+    /// Starts enumerating the recorders registered with `master`.
+    pub fn new(master: &IDiscMaster) -> Result<RecordersIter, BurnError> {
+        Ok(RecordersIter {
+            inner: unsafe { master.EnumDiscRecorders()? },
+        })
+    }
+}
+
+impl Iterator for RecordersIter {
+    type Item = Result<IDiscRecorder, BurnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut recorder: Option<IDiscRecorder> = None;
+        let mut fetched = 0u32;
+        match unsafe { self.inner.Next(1, &mut recorder, Some(&mut fetched)) } {
+            // `Next` succeeds with zero fetched (S_FALSE) at the end.
+            Ok(()) => match recorder {
+                Some(recorder) if fetched == 1 => Some(Ok(recorder)),
+                _ => None,
+            },
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
+
+/// Iterator over the recorder unique ids known to an `IDiscMaster2`.
+///
+/// `Count` is snapshotted at construction, so a device appearing or
+/// disappearing mid-iteration surfaces as an error on the affected index
+/// instead of shifting the sequence under the caller.
+pub struct DeviceIdsIter<'a> {
+    master: &'a IDiscMaster2,
+    index: i32,
+    count: i32,
+}
+
+impl Iterator for DeviceIdsIter<'_> {
+    type Item = Result<String, BurnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.count {
+            return None;
+        }
+        let item = unsafe { self.master.Item(self.index) };
+        self.index += 1;
+        Some(item.map(|id| bstr_to_string(&id)).map_err(BurnError::from))
+    }
+}
+
+/// Enumerates the recorder unique ids of every writable drive, for
+/// `IDiscRecorder2::InitializeDiscRecorder` lookups.
+pub fn device_ids(master: &IDiscMaster2) -> Result<DeviceIdsIter, BurnError> {
+    Ok(DeviceIdsIter {
+        master,
+        index: 0,
+        count: unsafe { master.Count()? },
+    })
+}
+
+/// Display-ready identity of one attached recorder, the building block for
+/// a drive-picker UI.
+#[derive(Clone, Debug)]
+pub struct RecorderSummary {
+    /// The IMAPI unique id, usable with `InitializeDiscRecorder`.
+    pub device_id: String,
+    pub vendor_id: String,
+    pub product_id: String,
+    pub product_revision: String,
+    pub volume_name: String,
+    /// Mount points of the drive, e.g. `D:\`.
+    pub volume_path_names: Vec<String>,
+}
+
+/// Enumerates every attached recorder and collects its display identity.
+/// Needs a live COM apartment on the calling thread.
+pub fn list_recorders() -> Result<Vec<RecorderSummary>, BurnError> {
+    let master = new_disc_master2()?;
+    let mut summaries = Vec::new();
+    for device_id in device_ids(&master)? {
+        let device_id = device_id?;
+        let recorder = new_disc_recorder2()?;
+        unsafe {
+            recorder.InitializeDiscRecorder(&string_to_bstr(&device_id))?;
+            summaries.push(RecorderSummary {
+                vendor_id: bstr_to_string(&recorder.VendorId()?).trim().to_string(),
+                product_id: bstr_to_string(&recorder.ProductId()?).trim().to_string(),
+                product_revision: bstr_to_string(&recorder.ProductRevision()?)
+                    .trim()
+                    .to_string(),
+                volume_name: bstr_to_string(&recorder.VolumeName()?),
+                volume_path_names: read_safearray_bstr(recorder.VolumePathNames()?)?,
+                device_id,
+            });
+        }
+    }
+    Ok(summaries)
+}
+
+/// Future resolving when a background burn finishes.
+///
+/// The blocking `Write` runs on a dedicated thread with its own
+/// single-threaded apartment; the writer interface crosses threads through
+/// an `AgileReference`, so the caller's apartment doesn't matter. Dropping
+/// the future before completion requests `CancelWrite`, which makes the
+/// background thread wind down at the next write checkpoint.
+pub struct WriteImageFuture {
+    receiver: tokio::sync::oneshot::Receiver<Result<(), BurnError>>,
+    format: AgileReference<IDiscFormat2Data>,
+    completed: Arc<AtomicBool>,
+}
+
+impl Future for WriteImageFuture {
+    type Output = Result<(), BurnError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        match Pin::new(&mut self.receiver).poll(cx) {
+            Poll::Ready(result) => {
+                self.completed.store(true, Ordering::SeqCst);
+                Poll::Ready(result.unwrap_or_else(|_| {
+                    Err(BurnError::Unsupported("the burn thread died unexpectedly"))
+                }))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for WriteImageFuture {
+    fn drop(&mut self) {
+        if self.completed.load(Ordering::SeqCst) {
+            return;
+        }
+        // Cancel from this thread; the burning thread is blocked in Write.
+        if let Ok(format) = self.format.resolve() {
+            if let Err(err) = unsafe { format.CancelWrite() } {
+                log::warn!("CancelWrite failed: {}", err);
+            }
+        }
+    }
+}
+
+impl DiscBurner {
+    /// Burns `source` on a background thread and resolves when the write
+    /// completes. Progress events advised on the writer keep flowing while
+    /// the burn runs.
+    pub fn write_image_async(
+        &self,
+        mut source: impl Read + Send + 'static,
+    ) -> Result<WriteImageFuture, BurnError> {
+        let agile = AgileReference::new(&self.format)?;
+        let worker_ref = agile.clone();
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        let completed = Arc::new(AtomicBool::new(false));
+        let completed_worker = completed.clone();
+
+        std::thread::spawn(move || {
+            let result = (|| -> Result<(), BurnError> {
+                let _com = ComApartment::enter()?;
+                let format = worker_ref.resolve()?;
+                let mut bytes = Vec::new();
+                source.read_to_end(&mut bytes)?;
+                let stream = memory_stream(&bytes)?;
+                match unsafe { format.Write(&stream) } {
+                    Ok(()) => Ok(()),
+                    Err(err) => {
+                        let recorder: Option<IDiscRecorder2Ex> = unsafe { format.Recorder() }
+                            .ok()
+                            .and_then(|r| r.cast().ok());
+                        Err(BurnFailure::capture(err, recorder.as_ref()).into_error())
+                    }
+                }
+            })();
+            completed_worker.store(true, Ordering::SeqCst);
+            let _ = sender.send(result);
+        });
+
+        Ok(WriteImageFuture {
+            receiver,
+            format: agile,
+            completed,
+        })
+    }
+}
@@ -0,0 +1,439 @@
+//! Helpers around `IFileSystemImage` configuration.
+
+use crate::error::{BurnError, ImapiError};
+use crate::factory::new_file_system_image;
+use crate::media::MediaType;
+use crate::util::{bstr_to_string, string_to_bstr};
+use windows::Win32::Foundation::VARIANT_BOOL;
+use windows::Win32::Storage::Imapi::{
+    FsiFileSystemISO9660, FsiFileSystemJoliet, FsiFileSystemUDF, FsiFileSystems, IDiscRecorder2,
+    IFileSystemImage, IFileSystemImageResult, IFsiDirectoryItem, IFsiFileItem,
+};
+use windows::Win32::System::Com::IStream;
+
+/// The three ways of telling a file system image how big its target is.
+pub enum Capacity {
+    /// Query the block count from a recorder with media present.
+    FromDevice(IDiscRecorder2),
+    /// Manual sector count, for headless image building without a drive.
+    Blocks(i32),
+    /// Use the IMAPI defaults for a given media type.
+    MediaType(MediaType),
+}
+
+/// Applies one of the capacity choices to the image.
+pub fn set_capacity(image: &IFileSystemImage, capacity: Capacity) -> Result<(), BurnError> {
+    unsafe {
+        match capacity {
+            Capacity::FromDevice(recorder) => image.SetMaxMediaBlocksFromDevice(&recorder)?,
+            Capacity::Blocks(blocks) => image.SetFreeMediaBlocks(blocks)?,
+            Capacity::MediaType(media_type) => {
+                image.ChooseImageDefaultsForMediaType(media_type.into())?
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `CreateResultImage` guard that refuses to build an image when the staged
+/// content no longer fits the configured capacity, instead of letting the
+/// write fail much later.
+pub fn create_result_image(image: &IFileSystemImage) -> Result<IFileSystemImageResult, BurnError> {
+    unsafe {
+        if image.FreeMediaBlocks()? <= 0 {
+            return Err(BurnError::CapacityNotSet);
+        }
+        Ok(image.CreateResultImage()?)
+    }
+}
+
+// Characters no IMAPI file system accepts in an item name.
+const RESERVED_NAME_CHARS: &[char] = &['\\', '/', ':', '*', '?', '"', '<', '>', '|'];
+// Longest name Joliet can represent; ISO9660 alone is stricter but IMAPI
+// images practically always include Joliet.
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Why an item name was rejected by the image.
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum NameError {
+    #[error("the name is empty")]
+    Empty,
+    #[error("name `{0}` is longer than the file systems allow")]
+    TooLong(String),
+    #[error("name `{name}` contains the illegal character `{character}`")]
+    IllegalCharacter { name: String, character: char },
+    #[error("an item named `{0}` already exists")]
+    Duplicate(String),
+}
+
+// Matches a failed item creation against the file-system naming rules, so
+// the bare IMAPI HRESULT gets a cause the caller can show. Falls back to the
+// original COM error when the name looks fine.
+fn classify_name_rejection(
+    image: &IFileSystemImage,
+    name: &str,
+    error: windows::core::Error,
+) -> BurnError {
+    if let Some(character) = name.chars().find(|c| RESERVED_NAME_CHARS.contains(c)) {
+        return NameError::IllegalCharacter {
+            name: name.to_string(),
+            character,
+        }
+        .into();
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        return NameError::TooLong(name.to_string()).into();
+    }
+    let duplicate = unsafe {
+        image
+            .Root()
+            .and_then(|root| root.Item(&string_to_bstr(name)))
+            .is_ok()
+    };
+    if duplicate {
+        return NameError::Duplicate(name.to_string()).into();
+    }
+    BurnError::Com(error)
+}
+
+/// `CreateFileItem` wrapper turning naming rejections into a typed
+/// `NameError` carrying the offending name.
+pub fn create_file(image: &IFileSystemImage, name: &str) -> Result<IFsiFileItem, BurnError> {
+    unsafe {
+        image
+            .CreateFileItem(&string_to_bstr(name))
+            .map_err(|err| classify_name_rejection(image, name, err))
+    }
+}
+
+/// `CreateDirectoryItem` wrapper, see `create_file`.
+pub fn create_dir(image: &IFileSystemImage, name: &str) -> Result<IFsiDirectoryItem, BurnError> {
+    unsafe {
+        image
+            .CreateDirectoryItem(&string_to_bstr(name))
+            .map_err(|err| classify_name_rejection(image, name, err))
+    }
+}
+
+/// The volume label carried over from an imported session, or `None` when
+/// the image has nothing imported.
+///
+/// This is distinct from `VolumeName`, which IMAPI may reset after an
+/// import; the imported name is what the disc was actually labelled.
+pub fn imported_volume_name(image: &IFileSystemImage) -> Result<Option<String>, BurnError> {
+    let name = unsafe { image.ImportedVolumeName()? };
+    if name.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(bstr_to_string(&name)))
+    }
+}
+
+/// Identifier IMAPI derives from the staged content, used to match a
+/// multisession continuation to the disc it belongs to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DiscId(pub String);
+
+impl std::fmt::Display for DiscId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The disc identifier of the staged image. Only meaningful once files have
+/// been staged: an empty image yields a degenerate id.
+pub fn disc_identifier(image: &IFileSystemImage) -> Result<DiscId, BurnError> {
+    let id = unsafe { image.CalculateDiscIdentifier()? };
+    Ok(DiscId(bstr_to_string(&id)))
+}
+
+/// Owned view of a `CreateResultImage` result, exposing the geometry
+/// consumers need to size the target media before burning the stream.
+pub struct ImageResult {
+    result: IFileSystemImageResult,
+}
+
+impl ImageResult {
+    /// Wraps an already created result.
+    pub fn new(result: IFileSystemImageResult) -> ImageResult {
+        ImageResult { result }
+    }
+
+    /// Builds the result image of `image`, with the same capacity guard as
+    /// `create_result_image`.
+    pub fn from_image(image: &IFileSystemImage) -> Result<ImageResult, BurnError> {
+        Ok(ImageResult::new(create_result_image(image)?))
+    }
+
+    /// Size of one image block in bytes.
+    pub fn block_size(&self) -> Result<u32, BurnError> {
+        Ok(unsafe { self.result.BlockSize()? } as u32)
+    }
+
+    /// Number of blocks in the image.
+    pub fn total_blocks(&self) -> Result<u64, BurnError> {
+        Ok(unsafe { self.result.TotalBlocks()? }.max(0) as u64)
+    }
+
+    /// Total image size in bytes.
+    pub fn image_size_bytes(&self) -> Result<u64, BurnError> {
+        Ok(u64::from(self.block_size()?) * self.total_blocks()?)
+    }
+
+    /// The image content, ready to hand to a writer.
+    pub fn into_stream(self) -> Result<IStream, BurnError> {
+        Ok(unsafe { self.result.ImageStream()? })
+    }
+}
+
+/// Accumulates `IFileSystemImage` settings and applies them in one go to a
+/// freshly created image, replacing the per-property HRESULT juggling.
+pub struct FileSystemImageBuilder {
+    volume_name: String,
+    file_systems: FsiFileSystems,
+    udf_revision: Option<i32>,
+    iso_interchange_level: Option<i32>,
+    free_media_blocks: Option<i32>,
+    stage_files: Option<bool>,
+}
+
+impl FileSystemImageBuilder {
+    /// Starts a builder producing a Joliet + ISO9660 image by default.
+    pub fn new(volume_name: &str) -> FileSystemImageBuilder {
+        FileSystemImageBuilder {
+            volume_name: volume_name.to_string(),
+            file_systems: FsiFileSystems(FsiFileSystemISO9660.0 | FsiFileSystemJoliet.0),
+            udf_revision: None,
+            iso_interchange_level: None,
+            free_media_blocks: None,
+            stage_files: None,
+        }
+    }
+
+    /// Selects the file systems to create.
+    pub fn file_systems(mut self, systems: FsiFileSystems) -> Self {
+        self.file_systems = systems;
+        self
+    }
+
+    /// UDF revision, e.g. `0x102` for 1.02.
+    pub fn udf_revision(mut self, revision: i32) -> Self {
+        self.udf_revision = Some(revision);
+        self
+    }
+
+    pub fn iso_interchange_level(mut self, level: i32) -> Self {
+        self.iso_interchange_level = Some(level);
+        self
+    }
+
+    pub fn free_media_blocks(mut self, blocks: i32) -> Self {
+        self.free_media_blocks = Some(blocks);
+        self
+    }
+
+    /// Whether files are staged to disk while the image is built.
+    pub fn stage_files(mut self, stage: bool) -> Self {
+        self.stage_files = Some(stage);
+        self
+    }
+
+    /// Creates the image and applies every accumulated setting. An empty
+    /// volume name is rejected before any COM object is created.
+    pub fn build(self) -> Result<IFileSystemImage, BurnError> {
+        if self.volume_name.trim().is_empty() {
+            return Err(NameError::Empty.into());
+        }
+        let image: IFileSystemImage = new_file_system_image()?;
+        unsafe {
+            image.SetVolumeName(&string_to_bstr(&self.volume_name))?;
+            image.SetFileSystemsToCreate(self.file_systems)?;
+            if let Some(revision) = self.udf_revision {
+                image.SetUDFRevision(revision)?;
+            }
+            if let Some(level) = self.iso_interchange_level {
+                image.SetISO9660InterchangeLevel(level)?;
+            }
+            if let Some(blocks) = self.free_media_blocks {
+                image.SetFreeMediaBlocks(blocks)?;
+            }
+            if let Some(stage) = self.stage_files {
+                image.SetStageFiles(VARIANT_BOOL::from(stage))?;
+            }
+        }
+        Ok(image)
+    }
+}
+
+/// The set of file systems found on an imported disc, wrapping the
+/// `FsiFileSystems` bits with named accessors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImportedFileSystems(pub FsiFileSystems);
+
+impl ImportedFileSystems {
+    pub fn has_iso9660(self) -> bool {
+        self.0 .0 & FsiFileSystemISO9660.0 != 0
+    }
+
+    pub fn has_joliet(self) -> bool {
+        self.0 .0 & FsiFileSystemJoliet.0 != 0
+    }
+
+    pub fn has_udf(self) -> bool {
+        self.0 .0 & FsiFileSystemUDF.0 != 0
+    }
+}
+
+impl std::fmt::Display for ImportedFileSystems {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names = Vec::new();
+        if self.has_iso9660() {
+            names.push("ISO9660");
+        }
+        if self.has_joliet() {
+            names.push("Joliet");
+        }
+        if self.has_udf() {
+            names.push("UDF");
+        }
+        if names.is_empty() {
+            names.push("none");
+        }
+        f.write_str(&names.join(" + "))
+    }
+}
+
+/// Imports the file system of the loaded disc into `image` and reports
+/// which file systems were found, so multisession continuation code can
+/// decide what to re-import.
+pub fn import_existing(image: &IFileSystemImage) -> Result<ImportedFileSystems, BurnError> {
+    Ok(ImportedFileSystems(unsafe { image.ImportFileSystem()? }))
+}
+
+/// How `configure_defaults_for` ended up configuring the image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageDefaults {
+    /// Defaults were chosen from the recorder and its loaded media.
+    FromRecorder {
+        file_systems: ImportedFileSystems,
+        udf_revision: i32,
+    },
+    /// No media was present; the caller-supplied media type was used.
+    FromMediaType(MediaType),
+}
+
+/// Picks file-system defaults for `image` from `recorder`, falling back to
+/// `ChooseImageDefaultsForMediaType(fallback)` when the drive has no media.
+/// The chosen configuration is read back and validated, so an image that
+/// ended up with no file systems at all fails here instead of at burn time.
+pub fn configure_defaults_for(
+    image: &IFileSystemImage,
+    recorder: &IDiscRecorder2,
+    fallback: MediaType,
+) -> Result<ImageDefaults, BurnError> {
+    match unsafe { image.ChooseImageDefaults(recorder) } {
+        Ok(()) => {
+            let file_systems = ImportedFileSystems(unsafe { image.FileSystemsToCreate()? });
+            if file_systems.0 .0 == 0 {
+                return Err(BurnError::Unsupported(
+                    "the recorder defaults selected no file systems",
+                ));
+            }
+            Ok(ImageDefaults::FromRecorder {
+                file_systems,
+                udf_revision: unsafe { image.UDFRevision()? },
+            })
+        }
+        Err(err) if ImapiError::from(err.clone()) == ImapiError::MediaNotPresent => {
+            unsafe { image.ChooseImageDefaultsForMediaType(fallback.into())? };
+            Ok(ImageDefaults::FromMediaType(fallback))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// RAII staging transaction over the change-point model of
+/// `IFileSystemImage`.
+///
+/// Captures the current change point on creation; dropping the guard rolls
+/// the image back to it unless [`commit`](ImageTransaction::commit) was
+/// called first.
+pub struct ImageTransaction<'a> {
+    image: &'a IFileSystemImage,
+    change_point: i32,
+    committed: bool,
+}
+
+impl<'a> ImageTransaction<'a> {
+    /// Opens a transaction at the image's current change point.
+    pub fn begin(image: &'a IFileSystemImage) -> Result<ImageTransaction<'a>, BurnError> {
+        let change_point = unsafe { image.ChangePoint()? };
+        Ok(ImageTransaction {
+            image,
+            change_point,
+            committed: false,
+        })
+    }
+
+    /// Keeps everything staged since the transaction opened and locks it in
+    /// with `LockInChangePoint`, so no later rollback can undo it.
+    pub fn commit(mut self) -> Result<(), BurnError> {
+        unsafe {
+            let current = self.image.ChangePoint()?;
+            self.image.LockInChangePoint(current)?;
+        }
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for ImageTransaction<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            // Best effort: a rollback failure has nowhere to surface from
+            // a drop.
+            let _ = unsafe { self.image.RollbackToChangePoint(self.change_point) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::com::ComApartment;
+    use crate::fsi::children;
+
+    #[test]
+    fn imported_file_system_names() {
+        let both = ImportedFileSystems(FsiFileSystems(
+            FsiFileSystemISO9660.0 | FsiFileSystemJoliet.0,
+        ));
+        assert!(both.has_iso9660() && both.has_joliet() && !both.has_udf());
+        assert_eq!(both.to_string(), "ISO9660 + Joliet");
+        assert_eq!(ImportedFileSystems(FsiFileSystems(0)).to_string(), "none");
+    }
+
+    #[test]
+    fn uncommitted_staging_is_rolled_back() {
+        let _com = ComApartment::enter().unwrap();
+        let image = new_file_system_image().unwrap();
+        let root = unsafe { image.Root() }.unwrap();
+        {
+            let _txn = ImageTransaction::begin(&image).unwrap();
+            unsafe { root.AddDirectory(&string_to_bstr("staged")) }.unwrap();
+            assert_eq!(children(&root).unwrap().count(), 1);
+        }
+        assert_eq!(children(&root).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn committed_staging_survives() {
+        let _com = ComApartment::enter().unwrap();
+        let image = new_file_system_image().unwrap();
+        let root = unsafe { image.Root() }.unwrap();
+        let txn = ImageTransaction::begin(&image).unwrap();
+        unsafe { root.AddDirectory(&string_to_bstr("kept")) }.unwrap();
+        txn.commit().unwrap();
+        assert_eq!(children(&root).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,379 @@
+//! Staging of local directory trees into a file system image.
+
+use crate::error::BurnError;
+use crate::fsi::{walk, FsiEntry};
+use crate::stream::file_stream;
+use crate::util::string_to_bstr;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use windows::core::ComInterface;
+use windows::Win32::Storage::Imapi::{FsiFileSystemISO9660, IFileSystemImage, IFsiDirectoryItem};
+
+// ISO9660 limits the directory hierarchy to eight levels.
+const ISO9660_MAX_DEPTH: usize = 8;
+
+/// What to do when the source tree contains a symlink.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Leave symlinks out of the image.
+    Skip,
+    /// Resolve the link and copy the target's contents at the link's path.
+    FollowCopy,
+    /// Fail the staging on the first symlink encountered.
+    Error,
+}
+
+impl Default for SymlinkPolicy {
+    fn default() -> Self {
+        // Preserves the historical behavior of folder staging.
+        SymlinkPolicy::Skip
+    }
+}
+
+/// Stages local folders into an `IFileSystemImage`.
+pub struct IsoBuilder {
+    image: IFileSystemImage,
+    symlink_policy: SymlinkPolicy,
+}
+
+impl IsoBuilder {
+    pub fn new(image: IFileSystemImage) -> Self {
+        IsoBuilder {
+            image,
+            symlink_policy: SymlinkPolicy::default(),
+        }
+    }
+
+    /// Selects how symlinks in the source tree are handled.
+    pub fn symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlink_policy = policy;
+        self
+    }
+
+    /// Hands back the underlying image, e.g. to create the result stream.
+    pub fn image(&self) -> &IFileSystemImage {
+        &self.image
+    }
+
+    /// Stages the content of `folder` at the image root.
+    pub fn add_folder(&self, folder: &Path) -> Result<(), BurnError> {
+        self.add_dir_filtered("", folder, |_| true).map(|_| ())
+    }
+
+    /// Stages the content of `folder` at `path_in_image` (empty for the
+    /// root), skipping every path the filter rejects. A rejected directory
+    /// is pruned entirely: its contents are never visited, which matters on
+    /// large trees. Returns the staged totals.
+    pub fn add_dir_filtered(
+        &self,
+        path_in_image: &str,
+        folder: &Path,
+        filter: impl Fn(&Path) -> bool,
+    ) -> Result<StageReport, BurnError> {
+        let mut item = unsafe { self.image.Root()? };
+        // Create the intermediate directories leading to the target.
+        for component in path_in_image
+            .split(&['/', '\\'][..])
+            .filter(|c| !c.is_empty())
+        {
+            let name = string_to_bstr(component);
+            unsafe {
+                item.AddDirectory(&name)?;
+                item = item.Item(&name)?.cast()?;
+            }
+        }
+        // Canonical paths of the directories currently being descended into,
+        // used to detect symlink cycles.
+        let mut in_progress = HashSet::new();
+        self.add_directory_contents(&item, folder, &filter, &mut in_progress)
+    }
+
+    fn add_directory_contents(
+        &self,
+        item: &IFsiDirectoryItem,
+        dir: &Path,
+        filter: &dyn Fn(&Path) -> bool,
+        in_progress: &mut HashSet<PathBuf>,
+    ) -> Result<StageReport, BurnError> {
+        let canonical = dir.canonicalize()?;
+        if !in_progress.insert(canonical.clone()) {
+            return Err(BurnError::SymlinkCycle(dir.to_path_buf()));
+        }
+
+        let mut report = StageReport::default();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if !filter(&path) {
+                continue;
+            }
+            if entry.file_type()?.is_symlink() {
+                match self.symlink_policy {
+                    SymlinkPolicy::Skip => continue,
+                    SymlinkPolicy::Error => return Err(BurnError::SymlinkRejected(path)),
+                    SymlinkPolicy::FollowCopy => {}
+                }
+            }
+            let name = string_to_bstr(entry.file_name().to_string_lossy().as_ref());
+            // std::fs::metadata follows symlinks, which is exactly what
+            // FollowCopy needs.
+            let metadata = std::fs::metadata(&path)?;
+            if metadata.is_dir() {
+                unsafe {
+                    item.AddDirectory(&name)?;
+                    let child: IFsiDirectoryItem = item.Item(&name)?.cast()?;
+                    report += self.add_directory_contents(&child, &path, filter, in_progress)?;
+                }
+            } else {
+                let stream = file_stream(&path)?;
+                unsafe { item.AddFile(&name, &stream)? };
+                report.files += 1;
+                report.bytes += metadata.len();
+            }
+        }
+
+        in_progress.remove(&canonical);
+        Ok(report)
+    }
+}
+
+/// Totals of a staging pass.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StageReport {
+    /// Number of files added to the image.
+    pub files: u64,
+    /// Their cumulative size in bytes.
+    pub bytes: u64,
+}
+
+impl std::ops::AddAssign for StageReport {
+    fn add_assign(&mut self, other: StageReport) {
+        self.files += other.files;
+        self.bytes += other.bytes;
+    }
+}
+
+/// Mirrors the tree at `src` under `dest` in the image root (empty for the
+/// root itself), skipping symlinks. Returns how many files and bytes were
+/// staged.
+pub fn stage_directory(
+    image: &IFileSystemImage,
+    src: &Path,
+    dest: &str,
+) -> Result<StageReport, BurnError> {
+    stage_directory_with_policy(image, src, dest, SymlinkPolicy::Skip)
+}
+
+/// Like `stage_directory` with an explicit symlink policy, for callers that
+/// want links followed (or treated as errors) instead of skipped.
+pub fn stage_directory_with_policy(
+    image: &IFileSystemImage,
+    src: &Path,
+    dest: &str,
+    policy: SymlinkPolicy,
+) -> Result<StageReport, BurnError> {
+    IsoBuilder::new(image.clone())
+        .symlink_policy(policy)
+        .add_dir_filtered(dest, src, |_| true)
+}
+
+/// One problem found by `IsoBuilder::validate`.
+#[derive(Clone, Debug)]
+pub enum ValidationIssue {
+    /// File name not representable in the selected file systems.
+    IllegalFileName(String),
+    /// Path nested deeper than ISO9660 allows.
+    PathTooDeep(String),
+    /// Staged content no longer fits the configured capacity.
+    CapacityExceeded { used_blocks: i32, free_blocks: i32 },
+    /// The volume name was never set.
+    EmptyVolumeName,
+    /// A COM call failed while inspecting the image.
+    Inspection(String),
+}
+
+// Filename check mirroring the IMAPI behavior closely enough for a pre-burn
+// problem list: the restricted character set only allows ISO d-characters,
+// strict compliance additionally caps the name length.
+fn file_name_is_legal(name: &str, restricted: bool, strict: bool) -> bool {
+    if restricted
+        && !name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_' || c == '.')
+    {
+        return false;
+    }
+    if strict && name.len() > 30 {
+        return false;
+    }
+    !name.is_empty()
+}
+
+impl IsoBuilder {
+    /// Checks the staged content for problems that would otherwise only
+    /// surface at `CreateResultImage` or burn time, aggregating every issue
+    /// instead of stopping at the first so a UI can show a complete list.
+    pub fn validate(&self) -> Result<(), Vec<ValidationIssue>> {
+        let mut issues = Vec::new();
+
+        let inspection = |err: BurnError| ValidationIssue::Inspection(err.to_string());
+
+        unsafe {
+            match self.image.VolumeName() {
+                Ok(name) if name.is_empty() => issues.push(ValidationIssue::EmptyVolumeName),
+                Ok(_) => {}
+                Err(err) => issues.push(inspection(err.into())),
+            }
+
+            match (self.image.FreeMediaBlocks(), self.image.UsedBlocks()) {
+                (Ok(free), Ok(used)) if free < 0 => {
+                    issues.push(ValidationIssue::CapacityExceeded {
+                        used_blocks: used,
+                        free_blocks: free,
+                    })
+                }
+                (Err(err), _) | (_, Err(err)) => issues.push(inspection(err.into())),
+                _ => {}
+            }
+
+            let iso9660 = match self.image.FileSystemsToCreate() {
+                Ok(systems) => systems.0 & FsiFileSystemISO9660.0 != 0,
+                Err(err) => {
+                    issues.push(inspection(err.into()));
+                    false
+                }
+            };
+            let restricted = self
+                .image
+                .UseRestrictedCharacterSet()
+                .map(|flag| flag.as_bool())
+                .unwrap_or(false);
+            let strict = self
+                .image
+                .StrictFileSystemCompliance()
+                .map(|flag| flag.as_bool())
+                .unwrap_or(false);
+
+            let result = match self.image.Root() {
+                Ok(root) => {
+                    let mut visitor = |entry: &FsiEntry| {
+                        if !entry.is_directory
+                            && !file_name_is_legal(&entry.name, restricted, strict)
+                        {
+                            issues.push(ValidationIssue::IllegalFileName(entry.full_path.clone()));
+                        }
+                        // The root itself counts as one level.
+                        if iso9660 && entry.full_path.matches('\\').count() > ISO9660_MAX_DEPTH {
+                            issues.push(ValidationIssue::PathTooDeep(entry.full_path.clone()));
+                        }
+                    };
+                    walk(&root, &mut visitor)
+                }
+                Err(err) => Err(err.into()),
+            };
+            if let Err(err) = result {
+                issues.push(inspection(err));
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+// Matches `name` against a simple glob pattern with `*` (any run) and `?`
+// (any single character).
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match (pattern.first(), name.first()) {
+            (None, None) => true,
+            (Some('*'), _) => {
+                matches(&pattern[1..], name) || (!name.is_empty() && matches(pattern, &name[1..]))
+            }
+            (Some('?'), Some(_)) => matches(&pattern[1..], &name[1..]),
+            (Some(p), Some(n)) if p == n => matches(&pattern[1..], &name[1..]),
+            _ => false,
+        }
+    }
+    matches(&pattern, &name)
+}
+
+/// Ignore list in the spirit of `.gitignore`, reduced to name globs: one
+/// pattern per line, `#` comments, `*` and `?` wildcards, matched against
+/// the file name of each staged path.
+#[derive(Clone, Debug, Default)]
+pub struct IsoIgnore {
+    patterns: Vec<String>,
+}
+
+impl IsoIgnore {
+    /// Parses patterns from the text of an `.isoignore` file.
+    pub fn from_text(text: &str) -> IsoIgnore {
+        IsoIgnore {
+            patterns: text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    /// Loads an `.isoignore` file from disk.
+    pub fn from_file(path: &Path) -> Result<IsoIgnore, BurnError> {
+        Ok(IsoIgnore::from_text(&std::fs::read_to_string(path)?))
+    }
+
+    /// Whether `path` should be excluded from the image.
+    pub fn matches(&self, path: &Path) -> bool {
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => return false,
+        };
+        self.patterns
+            .iter()
+            .any(|pattern| glob_match(pattern, name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn restricted_and_strict_name_rules() {
+        assert!(file_name_is_legal("README.TXT", true, true));
+        assert!(!file_name_is_legal("readme.txt", true, false));
+        assert!(!file_name_is_legal("", false, false));
+        assert!(file_name_is_legal("readme.txt", false, false));
+        assert!(!file_name_is_legal(
+            "a-name-that-is-way-too-long-for-strict-mode.txt",
+            false,
+            true
+        ));
+    }
+
+    #[test]
+    fn clutter_patterns() {
+        let ignore = IsoIgnore::from_text("# OS clutter\n.DS_Store\nThumbs.db\n*.tmp\n\n");
+        assert!(ignore.matches(Path::new("/src/.DS_Store")));
+        assert!(ignore.matches(Path::new("a/Thumbs.db")));
+        assert!(ignore.matches(Path::new("scratch.tmp")));
+        assert!(!ignore.matches(Path::new("notes.txt")));
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob_match("*.iso", "image.iso"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "ac"));
+        assert!(glob_match("*", "anything"));
+        assert!(!glob_match("*.iso", "image.img"));
+    }
+}
@@ -0,0 +1,185 @@
+//! Helpers for the legacy (v1) `IDiscRecorder` interface, for tools that
+//! still talk to the pre-IMAPIv2 API.
+
+use crate::error::BurnError;
+use std::fmt;
+use windows::Win32::Storage::Imapi::{
+    IDiscRecorder, DISC_RECORDER_STATE_FLAGS, MEDIA_FLAGS, MEDIA_TYPES, RECORDER_BURNING,
+    RECORDER_CDR, RECORDER_CDRW, RECORDER_DOING_NOTHING, RECORDER_OPENED, RECORDER_TYPES,
+};
+
+/// Thin wrapper over `DISC_RECORDER_STATE_FLAGS` replacing raw bit
+/// comparisons with named checks.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct RecorderState(pub DISC_RECORDER_STATE_FLAGS);
+
+impl RecorderState {
+    /// Queries the current state of `recorder`.
+    pub fn query(recorder: &IDiscRecorder) -> Result<RecorderState, BurnError> {
+        let mut flags = DISC_RECORDER_STATE_FLAGS::default();
+        unsafe { recorder.GetRecorderState(&mut flags)? };
+        Ok(RecorderState(flags))
+    }
+
+    /// Neither opened nor burning.
+    pub fn is_idle(self) -> bool {
+        self.0 == RECORDER_DOING_NOTHING
+    }
+
+    /// Opened for exclusive access by some client.
+    pub fn is_open(self) -> bool {
+        self.0 .0 & RECORDER_OPENED.0 != 0
+    }
+
+    /// A burn is in progress.
+    pub fn is_burning(self) -> bool {
+        self.0 .0 & RECORDER_BURNING.0 != 0
+    }
+
+    /// Busy in any way: opened or burning.
+    pub fn is_active(self) -> bool {
+        !self.is_idle()
+    }
+}
+
+impl fmt::Debug for RecorderState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names = Vec::new();
+        if self.is_open() {
+            names.push("OPENED");
+        }
+        if self.is_burning() {
+            names.push("BURNING");
+        }
+        if names.is_empty() {
+            names.push("DOING_NOTHING");
+        }
+        write!(f, "RecorderState({})", names.join(" | "))
+    }
+}
+
+/// Snapshot of the legacy `QueryMediaInfo` out parameters, with the five
+/// values as named fields instead of positional pointers.
+#[derive(Clone, Copy, Debug)]
+pub struct LegacyMediaInfo {
+    pub sessions: u8,
+    pub last_track: u8,
+    pub start_address: u32,
+    pub next_writable: u32,
+    pub free_blocks: u32,
+}
+
+/// Queries the media layout through the legacy v1 API.
+pub fn query_media_info(recorder: &IDiscRecorder) -> Result<LegacyMediaInfo, BurnError> {
+    let mut sessions = 0u8;
+    let mut last_track = 0u8;
+    let mut start_address = 0u32;
+    let mut next_writable = 0u32;
+    let mut free_blocks = 0u32;
+    unsafe {
+        recorder.QueryMediaInfo(
+            &mut sessions,
+            &mut last_track,
+            &mut start_address,
+            &mut next_writable,
+            &mut free_blocks,
+        )?;
+    }
+    Ok(LegacyMediaInfo {
+        sessions,
+        last_track,
+        start_address,
+        next_writable,
+        free_blocks,
+    })
+}
+
+/// The legacy media type and flags pair reported by `QueryMediaType`.
+pub fn query_media_type(recorder: &IDiscRecorder) -> Result<(MEDIA_TYPES, MEDIA_FLAGS), BurnError> {
+    let mut media_type = MEDIA_TYPES::default();
+    let mut media_flags = MEDIA_FLAGS::default();
+    unsafe { recorder.QueryMediaType(&mut media_type, &mut media_flags)? };
+    Ok((media_type, media_flags))
+}
+
+/// Classification of a legacy recorder, decoded from the `RECORDER_TYPES`
+/// bits. A rewriter advertises both the CD-R and CD-RW bits, so the
+/// rewritable bit wins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecorderKind {
+    CdR,
+    CdRw,
+    Unknown(i32),
+}
+
+impl From<RECORDER_TYPES> for RecorderKind {
+    fn from(value: RECORDER_TYPES) -> Self {
+        if value.0 & RECORDER_CDRW.0 != 0 {
+            RecorderKind::CdRw
+        } else if value.0 & RECORDER_CDR.0 != 0 {
+            RecorderKind::CdR
+        } else {
+            RecorderKind::Unknown(value.0)
+        }
+    }
+}
+
+impl fmt::Display for RecorderKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecorderKind::CdR => f.write_str("CD-R"),
+            RecorderKind::CdRw => f.write_str("CD-RW"),
+            RecorderKind::Unknown(raw) => write!(f, "recorder type {:#x}", raw),
+        }
+    }
+}
+
+/// The kind of drive behind the legacy `recorder`.
+pub fn recorder_type(recorder: &IDiscRecorder) -> Result<RecorderKind, BurnError> {
+    let mut kind = RECORDER_TYPES::default();
+    unsafe { recorder.GetRecorderType(&mut kind)? };
+    Ok(RecorderKind::from(kind))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recorder_kind_decoding() {
+        assert_eq!(RecorderKind::from(RECORDER_CDR), RecorderKind::CdR);
+        assert_eq!(RecorderKind::from(RECORDER_CDRW), RecorderKind::CdRw);
+        // A rewriter reports both bits; the rewritable bit wins.
+        let both = RECORDER_TYPES(RECORDER_CDR.0 | RECORDER_CDRW.0);
+        assert_eq!(RecorderKind::from(both), RecorderKind::CdRw);
+        assert_eq!(
+            RecorderKind::from(RECORDER_TYPES(0)),
+            RecorderKind::Unknown(0)
+        );
+        assert_eq!(RecorderKind::CdRw.to_string(), "CD-RW");
+    }
+
+    #[test]
+    fn state_flag_combinations() {
+        let idle = RecorderState(RECORDER_DOING_NOTHING);
+        assert!(idle.is_idle());
+        assert!(!idle.is_open());
+        assert!(!idle.is_burning());
+        assert!(!idle.is_active());
+        assert_eq!(format!("{:?}", idle), "RecorderState(DOING_NOTHING)");
+
+        let open = RecorderState(RECORDER_OPENED);
+        assert!(open.is_open() && open.is_active() && !open.is_burning());
+        assert_eq!(format!("{:?}", open), "RecorderState(OPENED)");
+
+        let burning = RecorderState(RECORDER_BURNING);
+        assert!(burning.is_burning() && burning.is_active() && !burning.is_open());
+        assert_eq!(format!("{:?}", burning), "RecorderState(BURNING)");
+
+        let both = RecorderState(DISC_RECORDER_STATE_FLAGS(
+            RECORDER_OPENED.0 | RECORDER_BURNING.0,
+        ));
+        assert!(both.is_open() && both.is_burning());
+        assert_eq!(format!("{:?}", both), "RecorderState(OPENED | BURNING)");
+    }
+}
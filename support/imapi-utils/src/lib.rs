@@ -0,0 +1,115 @@
+//! Safe wrappers around the IMAPIv2 disc mastering COM API.
+//!
+//! This crate is only meaningful on Windows hosts and compiles to an empty
+//! crate everywhere else.
+
+#![cfg(windows)]
+
+mod append;
+mod audio;
+mod boot;
+mod burn;
+mod cast;
+mod com;
+mod discinfo;
+mod dvd;
+mod erase;
+mod error;
+mod events;
+mod factory;
+mod fsi;
+mod highlevel;
+mod image;
+mod iso;
+mod legacy;
+mod media;
+mod mock;
+mod progress;
+mod rawcd;
+mod recorder;
+mod report;
+mod safearray;
+mod scsi;
+mod sense;
+mod speed;
+mod stream;
+mod toc;
+mod trackinfo;
+mod util;
+mod verify;
+mod watcher;
+
+pub use crate::append::{multisession_interfaces, set_multisession_interfaces, AppendSession};
+pub use crate::audio::{AudioDiscWriter, AUDIO_SECTOR_SIZE};
+pub use crate::boot::{BootEmulation, BootImageBuilder, BootOptions, BootPlatform};
+pub use crate::burn::{
+    burn, burn_image, burn_iso, burn_iso_file, burn_with_channel, burn_with_progress,
+    burn_with_retry, close_session, would_finalize, BurnOptions, RetryStrategy,
+};
+pub use crate::cast::{as_format2, try_as_data, try_as_erase, try_as_raw_cd, try_as_tao};
+pub use crate::com::{ComApartment, MarshaledRecorder};
+pub use crate::discinfo::{
+    disc_information, parse_disc_information, DiscInformation, DiscStatus, SessionState,
+};
+pub use crate::dvd::{send_dvd_structure, DvdStructure, DvdTimestamp};
+pub use crate::erase::{
+    ensure_writable, erase_media, DiscEraser, EraseMode, EraseProgress, EraseReport, EraseSession,
+};
+pub use crate::error::{BurnError, ImapiError};
+pub use crate::events::{advise_events, EventConnection, ProgressConnection, ProgressSink};
+pub use crate::factory::{
+    new_disc_master2, new_disc_recorder2, new_file_system_image, new_format2_data,
+    new_format2_erase,
+};
+pub use crate::fsi::{children, exists, walk, FsiEntry, FsiEntryKind, FsiItemsIter};
+pub use crate::highlevel::{
+    device_ids, list_recorders, DeviceIdsIter, DiscBurner, RecorderSummary, RecordersIter,
+    WriteImageFuture,
+};
+pub use crate::image::{
+    configure_defaults_for, create_dir, create_file, create_result_image, disc_identifier,
+    import_existing, imported_volume_name, set_capacity, Capacity, DiscId, FileSystemImageBuilder,
+    ImageDefaults, ImageResult, ImageTransaction, ImportedFileSystems, NameError,
+};
+pub use crate::iso::{
+    stage_directory, stage_directory_with_policy, IsoBuilder, IsoIgnore, StageReport,
+    SymlinkPolicy, ValidationIssue,
+};
+pub use crate::legacy::{
+    query_media_info, query_media_type, recorder_type, LegacyMediaInfo, RecorderKind, RecorderState,
+};
+pub use crate::media::{
+    current_media_is_supported_type, is_current_media_supported, is_recorder_supported,
+    media_heuristically_blank, media_info, media_physically_blank, media_write_mode,
+    supported_media_types, supported_media_types_raw, wait_for_blank_media, MediaGeneration,
+    MediaInfo, MediaType, WriteMode,
+};
+pub use crate::mock::{MockRecorderBuilder, MockRecorderCalls};
+pub use crate::progress::{
+    BurnPhase, BurnProgress, RawCdPhase, RawCdProgress, TaoPhase, TaoProgress,
+};
+pub use crate::rawcd::{RawCdSectorType, RawCdWriter};
+pub use crate::recorder::{
+    acquire_exclusive, capabilities, close_tray_with_timeout, eject_with_timeout,
+    feature_page_name, serial_number, supported_feature_pages, supported_profile_types,
+    suppress_mcn, volume_path_names, ExclusiveAccess, McnSuppressed, Profile, RecorderCapabilities,
+    RecorderInfo,
+};
+pub use crate::report::capability_report;
+pub use crate::scsi::{
+    get_mode_page, send_command_in, send_command_nodata, send_command_out, set_mode_page,
+    transfer_limits, IoLimits, ScsiCommand,
+};
+pub use crate::sense::{classify_burn_failure, BurnErrorKind, BurnFailure, SenseData};
+pub use crate::speed::{
+    supported_write_speeds, write_speed_descriptors, write_speed_status, WriteSpeedDescriptor,
+    WriteSpeedStatus,
+};
+pub use crate::stream::{MappedImage, ReadSeekStream, ResultImageStream, SizedRead, StreamSink};
+pub use crate::toc::{expected_toc, read_audio_toc, AudioToc, AudioTocTrack, Msf, TocEntry};
+pub use crate::trackinfo::{parse_track_information, track_information, TrackInfo};
+pub use crate::util::{bstr_to_string, string_to_bstr};
+pub use crate::verify::{
+    set_verification, verification, verify_disc, verify_written, VerificationLevel, VerifyOutcome,
+};
+pub use crate::watcher::{device_event_stream, DeviceEvent, DeviceEventStream, DeviceWatcher};
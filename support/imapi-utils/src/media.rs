@@ -0,0 +1,432 @@
+//! Friendly wrappers over the IMAPI media related enumerations.
+
+use crate::error::{BurnError, ImapiError};
+use crate::safearray::read_safearray_i32;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use windows::Win32::Storage::Imapi::*;
+
+/// The physical kind of media sitting in a recorder, mirroring
+/// `IMAPI_MEDIA_PHYSICAL_TYPE` without the C prefixes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaType {
+    Unknown,
+    CdRom,
+    CdR,
+    CdRw,
+    DvdRom,
+    DvdRam,
+    DvdPlusR,
+    DvdPlusRw,
+    DvdPlusRDualLayer,
+    DvdDashR,
+    DvdDashRw,
+    DvdDashRDualLayer,
+    Disk,
+    DvdPlusRwDualLayer,
+    HdDvdRom,
+    HdDvdR,
+    HdDvdRam,
+    BdRom,
+    BdR,
+    BdRe,
+}
+
+// Canonical names used by both `Display` and `FromStr`. `Unknown` is in the
+// table so serialized media info for unrecognized discs round trips.
+const MEDIA_TYPE_NAMES: &[(MediaType, &str)] = &[
+    (MediaType::Unknown, "Unknown"),
+    (MediaType::CdRom, "CD-ROM"),
+    (MediaType::CdR, "CD-R"),
+    (MediaType::CdRw, "CD-RW"),
+    (MediaType::DvdRom, "DVD-ROM"),
+    (MediaType::DvdRam, "DVD-RAM"),
+    (MediaType::DvdPlusR, "DVD+R"),
+    (MediaType::DvdPlusRw, "DVD+RW"),
+    (MediaType::DvdPlusRDualLayer, "DVD+R DL"),
+    (MediaType::DvdDashR, "DVD-R"),
+    (MediaType::DvdDashRw, "DVD-RW"),
+    (MediaType::DvdDashRDualLayer, "DVD-R DL"),
+    (MediaType::Disk, "Disk"),
+    (MediaType::DvdPlusRwDualLayer, "DVD+RW DL"),
+    (MediaType::HdDvdRom, "HD DVD-ROM"),
+    (MediaType::HdDvdR, "HD DVD-R"),
+    (MediaType::HdDvdRam, "HD DVD-RAM"),
+    (MediaType::BdRom, "BD-ROM"),
+    (MediaType::BdR, "BD-R"),
+    (MediaType::BdRe, "BD-RE"),
+];
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = MEDIA_TYPE_NAMES
+            .iter()
+            .find(|(media, _)| media == self)
+            .map(|(_, name)| *name)
+            .unwrap_or("Unknown");
+        f.write_str(name)
+    }
+}
+
+impl FromStr for MediaType {
+    type Err = BurnError;
+
+    /// Parses the names `Display` produces, ignoring ASCII case so config
+    /// files can spell `"dvd+rw"`.
+    fn from_str(value: &str) -> Result<MediaType, BurnError> {
+        MEDIA_TYPE_NAMES
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(value))
+            .map(|(media, _)| *media)
+            .ok_or(BurnError::Unsupported("unknown media type name"))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for MediaType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // The Display name, so JSON reads "DVD+R DL" instead of a raw code.
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MediaType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl From<IMAPI_MEDIA_PHYSICAL_TYPE> for MediaType {
+    fn from(value: IMAPI_MEDIA_PHYSICAL_TYPE) -> Self {
+        match value {
+            IMAPI_MEDIA_TYPE_CDROM => MediaType::CdRom,
+            IMAPI_MEDIA_TYPE_CDR => MediaType::CdR,
+            IMAPI_MEDIA_TYPE_CDRW => MediaType::CdRw,
+            IMAPI_MEDIA_TYPE_DVDROM => MediaType::DvdRom,
+            IMAPI_MEDIA_TYPE_DVDRAM => MediaType::DvdRam,
+            IMAPI_MEDIA_TYPE_DVDPLUSR => MediaType::DvdPlusR,
+            IMAPI_MEDIA_TYPE_DVDPLUSRW => MediaType::DvdPlusRw,
+            IMAPI_MEDIA_TYPE_DVDPLUSR_DUALLAYER => MediaType::DvdPlusRDualLayer,
+            IMAPI_MEDIA_TYPE_DVDDASHR => MediaType::DvdDashR,
+            IMAPI_MEDIA_TYPE_DVDDASHRW => MediaType::DvdDashRw,
+            IMAPI_MEDIA_TYPE_DVDDASHR_DUALLAYER => MediaType::DvdDashRDualLayer,
+            IMAPI_MEDIA_TYPE_DISK => MediaType::Disk,
+            IMAPI_MEDIA_TYPE_DVDPLUSRW_DUALLAYER => MediaType::DvdPlusRwDualLayer,
+            IMAPI_MEDIA_TYPE_HDDVDROM => MediaType::HdDvdRom,
+            IMAPI_MEDIA_TYPE_HDDVDR => MediaType::HdDvdR,
+            IMAPI_MEDIA_TYPE_HDDVDRAM => MediaType::HdDvdRam,
+            IMAPI_MEDIA_TYPE_BDROM => MediaType::BdRom,
+            IMAPI_MEDIA_TYPE_BDR => MediaType::BdR,
+            IMAPI_MEDIA_TYPE_BDRE => MediaType::BdRe,
+            _ => MediaType::Unknown,
+        }
+    }
+}
+
+impl From<MediaType> for IMAPI_MEDIA_PHYSICAL_TYPE {
+    fn from(value: MediaType) -> Self {
+        match value {
+            MediaType::Unknown => IMAPI_MEDIA_TYPE_UNKNOWN,
+            MediaType::CdRom => IMAPI_MEDIA_TYPE_CDROM,
+            MediaType::CdR => IMAPI_MEDIA_TYPE_CDR,
+            MediaType::CdRw => IMAPI_MEDIA_TYPE_CDRW,
+            MediaType::DvdRom => IMAPI_MEDIA_TYPE_DVDROM,
+            MediaType::DvdRam => IMAPI_MEDIA_TYPE_DVDRAM,
+            MediaType::DvdPlusR => IMAPI_MEDIA_TYPE_DVDPLUSR,
+            MediaType::DvdPlusRw => IMAPI_MEDIA_TYPE_DVDPLUSRW,
+            MediaType::DvdPlusRDualLayer => IMAPI_MEDIA_TYPE_DVDPLUSR_DUALLAYER,
+            MediaType::DvdDashR => IMAPI_MEDIA_TYPE_DVDDASHR,
+            MediaType::DvdDashRw => IMAPI_MEDIA_TYPE_DVDDASHRW,
+            MediaType::DvdDashRDualLayer => IMAPI_MEDIA_TYPE_DVDDASHR_DUALLAYER,
+            MediaType::Disk => IMAPI_MEDIA_TYPE_DISK,
+            MediaType::DvdPlusRwDualLayer => IMAPI_MEDIA_TYPE_DVDPLUSRW_DUALLAYER,
+            MediaType::HdDvdRom => IMAPI_MEDIA_TYPE_HDDVDROM,
+            MediaType::HdDvdR => IMAPI_MEDIA_TYPE_HDDVDR,
+            MediaType::HdDvdRam => IMAPI_MEDIA_TYPE_HDDVDRAM,
+            MediaType::BdRom => IMAPI_MEDIA_TYPE_BDROM,
+            MediaType::BdR => IMAPI_MEDIA_TYPE_BDR,
+            MediaType::BdRe => IMAPI_MEDIA_TYPE_BDRE,
+        }
+    }
+}
+
+/// Whether a re-burn on the media will overwrite (rewritable) or append
+/// (write-once).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriteMode {
+    Overwritable,
+    WriteOnce,
+    /// Unknown or non-recordable media; don't guess.
+    Unknown,
+}
+
+impl MediaType {
+    /// The write mode inherent to this media type.
+    pub fn write_mode(self) -> WriteMode {
+        match self {
+            MediaType::CdRw
+            | MediaType::DvdRam
+            | MediaType::DvdPlusRw
+            | MediaType::DvdPlusRwDualLayer
+            | MediaType::DvdDashRw
+            | MediaType::HdDvdRam
+            | MediaType::Disk
+            | MediaType::BdRe => WriteMode::Overwritable,
+            MediaType::CdR
+            | MediaType::DvdPlusR
+            | MediaType::DvdPlusRDualLayer
+            | MediaType::DvdDashR
+            | MediaType::DvdDashRDualLayer
+            | MediaType::HdDvdR
+            | MediaType::BdR => WriteMode::WriteOnce,
+            // Pressed/read-only or unrecognized media.
+            _ => WriteMode::Unknown,
+        }
+    }
+}
+
+/// Write mode of the media currently loaded in the recorder attached to
+/// `burner`. This tells callers whether `ForceMediaToBeClosed` is meaningful
+/// before they re-burn.
+pub fn media_write_mode(burner: &IDiscFormat2Data) -> Result<WriteMode, BurnError> {
+    Ok(MediaType::from(unsafe { burner.CurrentPhysicalMediaType()? }).write_mode())
+}
+
+/// Decodes the `SupportedMediaTypes` SAFEARRAY of `format` into the friendly
+/// enum, so an app can pre-check compatibility before loading media.
+pub fn supported_media_types(format: &IDiscFormat2) -> Result<Vec<MediaType>, BurnError> {
+    let psa = unsafe { format.SupportedMediaTypes()? };
+    Ok(read_safearray_i32(psa)?
+        .into_iter()
+        .map(|code| MediaType::from(IMAPI_MEDIA_PHYSICAL_TYPE(code)))
+        .collect())
+}
+
+/// Whether the media currently loaded in the recorder attached to `burner`
+/// is of a type `format` supports.
+pub fn current_media_is_supported_type(
+    format: &IDiscFormat2,
+    burner: &IDiscFormat2Data,
+) -> Result<bool, BurnError> {
+    let current = MediaType::from(unsafe { burner.CurrentPhysicalMediaType()? });
+    Ok(current != MediaType::Unknown && supported_media_types(format)?.contains(&current))
+}
+
+/// Whether `recorder` can be used with `format` at all, normalizing the
+/// raw `VARIANT_BOOL`.
+pub fn is_recorder_supported(
+    format: &IDiscFormat2,
+    recorder: &IDiscRecorder2,
+) -> Result<bool, BurnError> {
+    Ok(unsafe { format.IsRecorderSupported(recorder)? }.as_bool())
+}
+
+/// Whether the media loaded in `recorder` is usable with `format`.
+pub fn is_current_media_supported(
+    format: &IDiscFormat2,
+    recorder: &IDiscRecorder2,
+) -> Result<bool, BurnError> {
+    Ok(unsafe { format.IsCurrentMediaSupported(recorder)? }.as_bool())
+}
+
+/// Whether the loaded media is factory blank.
+pub fn media_physically_blank(format: &IDiscFormat2) -> Result<bool, BurnError> {
+    Ok(unsafe { format.MediaPhysicallyBlank()? }.as_bool())
+}
+
+/// Whether the loaded media looks blank after a quick erase, which zeroes
+/// only enough structure to make the disc appear empty.
+pub fn media_heuristically_blank(format: &IDiscFormat2) -> Result<bool, BurnError> {
+    Ok(unsafe { format.MediaHeuristicallyBlank()? }.as_bool())
+}
+
+// How often the drive is re-queried while waiting for media.
+const BLANK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// One poll of the wait loop: whether a blank disc usable with `burner` is
+// currently loaded.
+fn blank_media_loaded(
+    recorder: &IDiscRecorder2,
+    burner: &IDiscFormat2Data,
+    format: &IDiscFormat2,
+) -> Result<bool, BurnError> {
+    if !is_current_media_supported(format, recorder)? {
+        return Ok(false);
+    }
+    let status = unsafe { burner.CurrentMediaStatus()? };
+    if status.0 & IMAPI_FORMAT2_DATA_MEDIA_STATE_BLANK.0 != 0 {
+        return Ok(true);
+    }
+    media_physically_blank(format)
+}
+
+/// Blocks until a blank disc is loaded in `recorder`, for unattended
+/// multi-disc workflows that eject a finished disc and wait for the user to
+/// insert the next one. Polls `CurrentMediaStatus`/`MediaPhysicallyBlank`
+/// once per second; an empty tray or a non-blank disc keeps the wait going,
+/// any other drive failure is propagated. Errors with `Timeout` when
+/// `timeout` elapses without a blank disc appearing.
+pub fn wait_for_blank_media(
+    recorder: &IDiscRecorder2,
+    burner: &IDiscFormat2Data,
+    timeout: Duration,
+) -> Result<(), BurnError> {
+    let format = crate::cast::as_format2(burner)?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        match blank_media_loaded(recorder, burner, &format) {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            // The drive answers with "no media" while the tray is empty.
+            Err(BurnError::Com(err))
+                if ImapiError::from(err.clone()) == ImapiError::MediaNotPresent => {}
+            Err(err) => return Err(err),
+        }
+        if Instant::now() >= deadline {
+            return Err(BurnError::Timeout);
+        }
+        std::thread::sleep(BLANK_POLL_INTERVAL);
+    }
+}
+
+/// Opaque token identifying the media that was in the drive when the token
+/// was captured, closing the gap between a support check and the actual
+/// write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MediaGeneration(u64);
+
+impl MediaGeneration {
+    /// Captures a token for the current media. This costs a single READ DISC
+    /// INFORMATION pass-through, so it's cheap enough to run right before a
+    /// burn.
+    pub fn capture(recorder: &IDiscRecorder2Ex) -> Result<MediaGeneration, BurnError> {
+        let info = crate::scsi::get_disc_information_raw(recorder)?;
+        let mut hasher = DefaultHasher::new();
+        info.hash(&mut hasher);
+        Ok(MediaGeneration(hasher.finish()))
+    }
+
+    /// Errors with `MediaChanged` if the media in the drive no longer
+    /// matches this token.
+    pub fn revalidate(&self, recorder: &IDiscRecorder2Ex) -> Result<(), BurnError> {
+        if MediaGeneration::capture(recorder)? == *self {
+            Ok(())
+        } else {
+            Err(BurnError::MediaChanged)
+        }
+    }
+}
+
+/// The raw `SupportedMediaTypes` codes of `format`, for callers that need
+/// the untranslated enum (e.g. to pass back into other IMAPI properties).
+/// A null or empty array decodes to an empty vector.
+pub fn supported_media_types_raw(
+    format: &IDiscFormat2,
+) -> Result<Vec<IMAPI_MEDIA_PHYSICAL_TYPE>, BurnError> {
+    let psa = unsafe { format.SupportedMediaTypes()? };
+    Ok(read_safearray_i32(psa)?
+        .into_iter()
+        .map(IMAPI_MEDIA_PHYSICAL_TYPE)
+        .collect())
+}
+
+/// One-shot snapshot of the media in the recorder attached to a writer, so
+/// UI display needs a single call instead of five property reads. The
+/// reads are not atomic at the drive level, but they come from one point in
+/// time as far as IMAPI's caching allows.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaInfo {
+    pub total_sectors: i32,
+    pub free_sectors: i32,
+    pub next_writable_address: i32,
+    pub write_protected: bool,
+    pub physical_type: MediaType,
+    #[cfg_attr(feature = "serde", serde(with = "media_state_serde"))]
+    pub current_status: IMAPI_FORMAT2_DATA_MEDIA_STATE,
+}
+
+// The media-state flags have no name worth serializing, so they round trip
+// as the raw bits.
+#[cfg(feature = "serde")]
+mod media_state_serde {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use windows::Win32::Storage::Imapi::IMAPI_FORMAT2_DATA_MEDIA_STATE;
+
+    pub fn serialize<S: Serializer>(
+        value: &IMAPI_FORMAT2_DATA_MEDIA_STATE,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i32(value.0)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<IMAPI_FORMAT2_DATA_MEDIA_STATE, D::Error> {
+        Ok(IMAPI_FORMAT2_DATA_MEDIA_STATE(i32::deserialize(
+            deserializer,
+        )?))
+    }
+}
+
+/// Reads the full media snapshot from `burner`.
+pub fn media_info(burner: &IDiscFormat2Data) -> Result<MediaInfo, BurnError> {
+    unsafe {
+        Ok(MediaInfo {
+            total_sectors: burner.TotalSectorsOnMedia()?,
+            free_sectors: burner.FreeSectorsOnMedia()?,
+            next_writable_address: burner.NextWritableAddress()?,
+            // The COM variant boolean is normalized to a Rust bool here.
+            write_protected: burner.WriteProtectStatus()?.as_bool(),
+            physical_type: MediaType::from(burner.CurrentPhysicalMediaType()?),
+            current_status: burner.CurrentMediaStatus()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn media_type_names_round_trip() {
+        for (media, name) in MEDIA_TYPE_NAMES {
+            assert_eq!(media.to_string(), *name);
+            assert_eq!(name.parse::<MediaType>().unwrap(), *media);
+        }
+        // Unrecognized media must round trip too, or serialized MediaInfo
+        // for it can never be read back.
+        assert_eq!("Unknown".parse::<MediaType>().unwrap(), MediaType::Unknown);
+        assert!("floppy".parse::<MediaType>().is_err());
+    }
+
+    #[test]
+    fn parsing_ignores_case() {
+        assert_eq!("dvd+rw".parse::<MediaType>().unwrap(), MediaType::DvdPlusRw);
+        assert_eq!("bd-re".parse::<MediaType>().unwrap(), MediaType::BdRe);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn media_info_round_trips_through_json() {
+        let info = MediaInfo {
+            total_sectors: 2_295_104,
+            free_sectors: 1_000_000,
+            next_writable_address: 1_295_104,
+            write_protected: false,
+            physical_type: MediaType::DvdPlusRDualLayer,
+            current_status: IMAPI_FORMAT2_DATA_MEDIA_STATE(0x4),
+        };
+        let json = serde_json::to_string(&info).unwrap();
+        assert!(json.contains("\"DVD+R DL\""));
+        let back: MediaInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.physical_type, info.physical_type);
+        assert_eq!(back.total_sectors, info.total_sectors);
+        assert_eq!(back.current_status, info.current_status);
+    }
+}
@@ -0,0 +1,257 @@
+//! A canned `IDiscRecorder2` for exercising recorder-selection logic
+//! without a physical drive.
+
+use crate::safearray::make_safearray_i32;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use windows::core::{implement, Result as ComResult, BSTR, GUID, PCWSTR};
+use windows::Win32::Foundation::{E_NOTIMPL, VARIANT_BOOL};
+use windows::Win32::Storage::Imapi::{IDiscRecorder2, IDiscRecorder2_Impl};
+use windows::Win32::System::Com::{
+    IDispatch_Impl, ITypeInfo, DISPATCH_FLAGS, DISPPARAMS, EXCEPINFO, SAFEARRAY,
+};
+use windows::Win32::System::Variant::VARIANT;
+
+/// Call counters shared with a `MockRecorder`, so a test can assert how the
+/// code under test drove the tray.
+#[derive(Clone, Default)]
+pub struct MockRecorderCalls {
+    eject_media: Arc<AtomicUsize>,
+    close_tray: Arc<AtomicUsize>,
+    exclusive_acquires: Arc<AtomicUsize>,
+    exclusive_releases: Arc<AtomicUsize>,
+    mcn_disables: Arc<AtomicUsize>,
+    mcn_enables: Arc<AtomicUsize>,
+}
+
+impl MockRecorderCalls {
+    /// How many times `EjectMedia` was called.
+    pub fn eject_media(&self) -> usize {
+        self.eject_media.load(Ordering::SeqCst)
+    }
+
+    /// How many times `CloseTray` was called.
+    pub fn close_tray(&self) -> usize {
+        self.close_tray.load(Ordering::SeqCst)
+    }
+
+    /// How many times `AcquireExclusiveAccess` was called.
+    pub fn exclusive_acquires(&self) -> usize {
+        self.exclusive_acquires.load(Ordering::SeqCst)
+    }
+
+    /// How many times `ReleaseExclusiveAccess` was called.
+    pub fn exclusive_releases(&self) -> usize {
+        self.exclusive_releases.load(Ordering::SeqCst)
+    }
+
+    /// How many times `DisableMcn` was called.
+    pub fn mcn_disables(&self) -> usize {
+        self.mcn_disables.load(Ordering::SeqCst)
+    }
+
+    /// How many times `EnableMcn` was called.
+    pub fn mcn_enables(&self) -> usize {
+        self.mcn_enables.load(Ordering::SeqCst)
+    }
+}
+
+/// Builder for the canned responses of a mock recorder. Everything not
+/// configured answers `E_NOTIMPL`, which keeps tests honest about what they
+/// depend on.
+pub struct MockRecorderBuilder {
+    vendor_id: String,
+    product_id: String,
+    supported_profiles: Vec<i32>,
+    can_load_media: bool,
+}
+
+impl Default for MockRecorderBuilder {
+    fn default() -> Self {
+        MockRecorderBuilder {
+            vendor_id: "MOCK".to_string(),
+            product_id: "RECORDER".to_string(),
+            supported_profiles: Vec::new(),
+            can_load_media: true,
+        }
+    }
+}
+
+impl MockRecorderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vendor_id(mut self, vendor_id: &str) -> Self {
+        self.vendor_id = vendor_id.to_string();
+        self
+    }
+
+    pub fn product_id(mut self, product_id: &str) -> Self {
+        self.product_id = product_id.to_string();
+        self
+    }
+
+    /// Raw `IMAPI_PROFILE_TYPE` codes returned from `SupportedProfiles`.
+    pub fn supported_profiles(mut self, profiles: &[i32]) -> Self {
+        self.supported_profiles = profiles.to_vec();
+        self
+    }
+
+    pub fn can_load_media(mut self, can_load_media: bool) -> Self {
+        self.can_load_media = can_load_media;
+        self
+    }
+
+    /// Builds the COM object plus the counters recording tray calls.
+    pub fn build(self) -> (IDiscRecorder2, MockRecorderCalls) {
+        let calls = MockRecorderCalls::default();
+        let recorder: IDiscRecorder2 = MockRecorder {
+            vendor_id: self.vendor_id,
+            product_id: self.product_id,
+            supported_profiles: self.supported_profiles,
+            can_load_media: self.can_load_media,
+            calls: calls.clone(),
+        }
+        .into();
+        (recorder, calls)
+    }
+}
+
+#[implement(IDiscRecorder2)]
+struct MockRecorder {
+    vendor_id: String,
+    product_id: String,
+    supported_profiles: Vec<i32>,
+    can_load_media: bool,
+    calls: MockRecorderCalls,
+}
+
+#[allow(non_snake_case)]
+impl IDiscRecorder2_Impl for MockRecorder {
+    fn EjectMedia(&self) -> ComResult<()> {
+        self.calls.eject_media.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn CloseTray(&self) -> ComResult<()> {
+        self.calls.close_tray.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn AcquireExclusiveAccess(&self, _force: VARIANT_BOOL, _clientname: &BSTR) -> ComResult<()> {
+        self.calls.exclusive_acquires.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn ReleaseExclusiveAccess(&self) -> ComResult<()> {
+        self.calls.exclusive_releases.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn DisableMcn(&self) -> ComResult<()> {
+        self.calls.mcn_disables.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn EnableMcn(&self) -> ComResult<()> {
+        self.calls.mcn_enables.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn InitializeDiscRecorder(&self, _recorderuniqueid: &BSTR) -> ComResult<()> {
+        Ok(())
+    }
+
+    fn ActiveDiscRecorder(&self) -> ComResult<BSTR> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn VendorId(&self) -> ComResult<BSTR> {
+        Ok(BSTR::from(self.vendor_id.as_str()))
+    }
+
+    fn ProductId(&self) -> ComResult<BSTR> {
+        Ok(BSTR::from(self.product_id.as_str()))
+    }
+
+    fn ProductRevision(&self) -> ComResult<BSTR> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn VolumeName(&self) -> ComResult<BSTR> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn VolumePathNames(&self) -> ComResult<*mut SAFEARRAY> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn DeviceCanLoadMedia(&self) -> ComResult<VARIANT_BOOL> {
+        Ok(VARIANT_BOOL::from(self.can_load_media))
+    }
+
+    fn LegacyDeviceNumber(&self) -> ComResult<i32> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn SupportedFeaturePages(&self) -> ComResult<*mut SAFEARRAY> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn CurrentFeaturePages(&self) -> ComResult<*mut SAFEARRAY> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn SupportedProfiles(&self) -> ComResult<*mut SAFEARRAY> {
+        make_safearray_i32(&self.supported_profiles)
+    }
+
+    fn CurrentProfiles(&self) -> ComResult<*mut SAFEARRAY> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn SupportedModePages(&self) -> ComResult<*mut SAFEARRAY> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn ExclusiveAccessOwner(&self) -> ComResult<BSTR> {
+        Err(E_NOTIMPL.into())
+    }
+}
+
+impl IDispatch_Impl for MockRecorder {
+    fn GetTypeInfoCount(&self) -> ComResult<u32> {
+        Ok(0)
+    }
+
+    fn GetTypeInfo(&self, _itinfo: u32, _lcid: u32) -> ComResult<ITypeInfo> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn GetIDsOfNames(
+        &self,
+        _riid: *const GUID,
+        _rgsznames: *const PCWSTR,
+        _cnames: u32,
+        _lcid: u32,
+        _rgdispid: *mut i32,
+    ) -> ComResult<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn Invoke(
+        &self,
+        _dispidmember: i32,
+        _riid: *const GUID,
+        _lcid: u32,
+        _wflags: DISPATCH_FLAGS,
+        _pdispparams: *const DISPPARAMS,
+        _pvarresult: *mut VARIANT,
+        _pexcepinfo: *mut EXCEPINFO,
+        _puargerr: *mut u32,
+    ) -> ComResult<()> {
+        Err(E_NOTIMPL.into())
+    }
+}
@@ -0,0 +1,314 @@
+//! Typed burn progress built from `IDiscFormat2DataEventArgs`.
+
+use crate::error::BurnError;
+use std::time::Duration;
+use windows::Win32::Storage::Imapi::{
+    IDiscFormat2DataEventArgs, IDiscFormat2RawCDEventArgs, IDiscFormat2TrackAtOnceEventArgs,
+    IMAPI_FORMAT2_DATA_WRITE_ACTION, IMAPI_FORMAT2_DATA_WRITE_ACTION_CALIBRATING_POWER,
+    IMAPI_FORMAT2_DATA_WRITE_ACTION_COMPLETED, IMAPI_FORMAT2_DATA_WRITE_ACTION_FINALIZATION,
+    IMAPI_FORMAT2_DATA_WRITE_ACTION_FORMATTING_MEDIA,
+    IMAPI_FORMAT2_DATA_WRITE_ACTION_INITIALIZING_HARDWARE,
+    IMAPI_FORMAT2_DATA_WRITE_ACTION_VALIDATING_MEDIA, IMAPI_FORMAT2_DATA_WRITE_ACTION_VERIFYING,
+    IMAPI_FORMAT2_DATA_WRITE_ACTION_WRITING_DATA, IMAPI_FORMAT2_RAW_CD_WRITE_ACTION,
+    IMAPI_FORMAT2_RAW_CD_WRITE_ACTION_FINISHING, IMAPI_FORMAT2_RAW_CD_WRITE_ACTION_PREPARING,
+    IMAPI_FORMAT2_RAW_CD_WRITE_ACTION_WRITING, IMAPI_FORMAT2_TAO_WRITE_ACTION,
+    IMAPI_FORMAT2_TAO_WRITE_ACTION_FINISHING, IMAPI_FORMAT2_TAO_WRITE_ACTION_PREPARING,
+    IMAPI_FORMAT2_TAO_WRITE_ACTION_VERIFYING, IMAPI_FORMAT2_TAO_WRITE_ACTION_WRITING,
+};
+
+/// Phase of a data burn, decoded from the write action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BurnPhase {
+    ValidatingMedia,
+    FormattingMedia,
+    InitializingHardware,
+    CalibratingPower,
+    Writing,
+    Finalizing,
+    Verifying,
+    Completed,
+    Unknown,
+}
+
+impl From<IMAPI_FORMAT2_DATA_WRITE_ACTION> for BurnPhase {
+    fn from(value: IMAPI_FORMAT2_DATA_WRITE_ACTION) -> Self {
+        match value {
+            IMAPI_FORMAT2_DATA_WRITE_ACTION_VALIDATING_MEDIA => BurnPhase::ValidatingMedia,
+            IMAPI_FORMAT2_DATA_WRITE_ACTION_FORMATTING_MEDIA => BurnPhase::FormattingMedia,
+            IMAPI_FORMAT2_DATA_WRITE_ACTION_INITIALIZING_HARDWARE => {
+                BurnPhase::InitializingHardware
+            }
+            IMAPI_FORMAT2_DATA_WRITE_ACTION_CALIBRATING_POWER => BurnPhase::CalibratingPower,
+            IMAPI_FORMAT2_DATA_WRITE_ACTION_WRITING_DATA => BurnPhase::Writing,
+            IMAPI_FORMAT2_DATA_WRITE_ACTION_FINALIZATION => BurnPhase::Finalizing,
+            IMAPI_FORMAT2_DATA_WRITE_ACTION_VERIFYING => BurnPhase::Verifying,
+            IMAPI_FORMAT2_DATA_WRITE_ACTION_COMPLETED => BurnPhase::Completed,
+            _ => BurnPhase::Unknown,
+        }
+    }
+}
+
+// Position within a sector range as a percentage, or None when the range is
+// empty so we never divide by zero.
+fn sector_percent(start_lba: i32, sector_count: i32, position: i32) -> Option<f64> {
+    if sector_count <= 0 {
+        return None;
+    }
+    let done = f64::from((position - start_lba).clamp(0, sector_count));
+    Some(done / f64::from(sector_count) * 100.0)
+}
+
+/// One progress notification during a data burn.
+#[derive(Clone, Copy, Debug)]
+pub struct BurnProgress {
+    pub phase: BurnPhase,
+    pub elapsed: Duration,
+    /// Drive estimate of the time left, when it provides one.
+    pub remaining: Option<Duration>,
+    /// Total time estimate, when the drive provides one.
+    pub estimated_total: Option<Duration>,
+    pub start_lba: i32,
+    pub sector_count: i32,
+    pub last_written_lba: i32,
+    pub last_read_lba: i32,
+    /// Write completion in percent, `None` while the sector range is still
+    /// unknown.
+    pub write_percent: Option<f64>,
+    /// Read-back verify completion, only `Some` while in the verify phase so
+    /// UIs can show that segment distinctly.
+    pub verifying_percent: Option<f64>,
+}
+
+impl BurnProgress {
+    pub(crate) fn from_event_args(
+        args: &IDiscFormat2DataEventArgs,
+    ) -> Result<BurnProgress, BurnError> {
+        unsafe {
+            let phase = BurnPhase::from(args.CurrentAction()?);
+            let start_lba = args.StartLba()?;
+            let sector_count = args.SectorCount()?;
+            let last_written_lba = args.LastWrittenLba()?;
+            let last_read_lba = args.LastReadLba()?;
+            let elapsed = args.ElapsedTime()?;
+            let remaining = args.RemainingTime()?;
+            let total = args.TotalTime()?;
+            Ok(BurnProgress {
+                phase,
+                elapsed: Duration::from_secs(elapsed.max(0) as u64),
+                remaining: if remaining > 0 {
+                    Some(Duration::from_secs(remaining as u64))
+                } else {
+                    None
+                },
+                estimated_total: if total > 0 {
+                    Some(Duration::from_secs(total as u64))
+                } else {
+                    None
+                },
+                start_lba,
+                sector_count,
+                last_written_lba,
+                last_read_lba,
+                write_percent: sector_percent(start_lba, sector_count, last_written_lba),
+                verifying_percent: if phase == BurnPhase::Verifying {
+                    sector_percent(start_lba, sector_count, last_read_lba)
+                } else {
+                    None
+                },
+            })
+        }
+    }
+
+    /// Overall completion for a progress bar. With `verify_expected` the
+    /// write phase maps to the first half and the verify phase to the second
+    /// one; drives that never enter the verify phase still end at 100% via
+    /// the `Completed` action, collapsing back to a single segment.
+    pub fn overall_percent(&self, verify_expected: bool) -> Option<f64> {
+        match self.phase {
+            BurnPhase::Completed => Some(100.0),
+            BurnPhase::Verifying => self.verifying_percent.map(|verify| 50.0 + verify / 2.0),
+            _ => self
+                .write_percent
+                .map(|write| if verify_expected { write / 2.0 } else { write }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn progress(phase: BurnPhase, written: i32, read: i32) -> BurnProgress {
+        BurnProgress {
+            phase,
+            elapsed: Duration::from_secs(10),
+            remaining: None,
+            estimated_total: None,
+            start_lba: 0,
+            sector_count: 1000,
+            last_written_lba: written,
+            last_read_lba: read,
+            write_percent: sector_percent(0, 1000, written),
+            verifying_percent: if phase == BurnPhase::Verifying {
+                sector_percent(0, 1000, read)
+            } else {
+                None
+            },
+        }
+    }
+
+    #[test]
+    fn split_segments_when_verify_expected() {
+        let writing = progress(BurnPhase::Writing, 500, 0);
+        assert_eq!(writing.overall_percent(true), Some(25.0));
+        assert_eq!(writing.overall_percent(false), Some(50.0));
+
+        let verifying = progress(BurnPhase::Verifying, 1000, 500);
+        assert_eq!(verifying.overall_percent(true), Some(75.0));
+
+        let completed = progress(BurnPhase::Completed, 1000, 1000);
+        assert_eq!(completed.overall_percent(true), Some(100.0));
+        assert_eq!(completed.overall_percent(false), Some(100.0));
+    }
+
+    #[test]
+    fn empty_sector_range_yields_no_percent() {
+        assert_eq!(sector_percent(0, 0, 10), None);
+        assert_eq!(sector_percent(100, 1000, 50), Some(0.0));
+    }
+}
+
+/// Phase of a track-at-once audio burn, decoded from
+/// `IMAPI_FORMAT2_TAO_WRITE_ACTION`. Codes this build doesn't know about are
+/// preserved as `Unknown` so newer drives stay readable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaoPhase {
+    Preparing,
+    Writing,
+    Finishing,
+    Verifying,
+    Unknown(i32),
+}
+
+impl From<IMAPI_FORMAT2_TAO_WRITE_ACTION> for TaoPhase {
+    fn from(value: IMAPI_FORMAT2_TAO_WRITE_ACTION) -> Self {
+        match value {
+            IMAPI_FORMAT2_TAO_WRITE_ACTION_PREPARING => TaoPhase::Preparing,
+            IMAPI_FORMAT2_TAO_WRITE_ACTION_WRITING => TaoPhase::Writing,
+            IMAPI_FORMAT2_TAO_WRITE_ACTION_FINISHING => TaoPhase::Finishing,
+            IMAPI_FORMAT2_TAO_WRITE_ACTION_VERIFYING => TaoPhase::Verifying,
+            other => TaoPhase::Unknown(other.0),
+        }
+    }
+}
+
+impl std::fmt::Display for TaoPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TaoPhase::Preparing => write!(f, "preparing the media"),
+            TaoPhase::Writing => write!(f, "writing the track"),
+            TaoPhase::Finishing => write!(f, "finishing the track"),
+            TaoPhase::Verifying => write!(f, "verifying the track"),
+            TaoPhase::Unknown(code) => write!(f, "unknown action {}", code),
+        }
+    }
+}
+
+/// Phase of a raw (disc-at-once) CD burn, decoded from
+/// `IMAPI_FORMAT2_RAW_CD_WRITE_ACTION`, with the same `Unknown` escape hatch
+/// as `TaoPhase`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RawCdPhase {
+    Preparing,
+    Writing,
+    Finishing,
+    Unknown(i32),
+}
+
+impl From<IMAPI_FORMAT2_RAW_CD_WRITE_ACTION> for RawCdPhase {
+    fn from(value: IMAPI_FORMAT2_RAW_CD_WRITE_ACTION) -> Self {
+        match value {
+            IMAPI_FORMAT2_RAW_CD_WRITE_ACTION_PREPARING => RawCdPhase::Preparing,
+            IMAPI_FORMAT2_RAW_CD_WRITE_ACTION_WRITING => RawCdPhase::Writing,
+            IMAPI_FORMAT2_RAW_CD_WRITE_ACTION_FINISHING => RawCdPhase::Finishing,
+            other => RawCdPhase::Unknown(other.0),
+        }
+    }
+}
+
+impl std::fmt::Display for RawCdPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RawCdPhase::Preparing => write!(f, "preparing the media"),
+            RawCdPhase::Writing => write!(f, "writing the disc"),
+            RawCdPhase::Finishing => write!(f, "finishing the disc"),
+            RawCdPhase::Unknown(code) => write!(f, "unknown action {}", code),
+        }
+    }
+}
+
+/// One progress notification during a track-at-once audio burn.
+#[derive(Clone, Copy, Debug)]
+pub struct TaoProgress {
+    pub phase: TaoPhase,
+    pub elapsed: Duration,
+    pub start_lba: i32,
+    pub sector_count: i32,
+    pub last_written_lba: i32,
+    /// Track completion in percent, `None` while the sector range is still
+    /// unknown.
+    pub percent: Option<f64>,
+}
+
+impl TaoProgress {
+    pub(crate) fn from_event_args(
+        args: &IDiscFormat2TrackAtOnceEventArgs,
+    ) -> Result<TaoProgress, BurnError> {
+        unsafe {
+            let start_lba = args.StartLba()?;
+            let sector_count = args.SectorCount()?;
+            let last_written_lba = args.LastWrittenLba()?;
+            Ok(TaoProgress {
+                phase: TaoPhase::from(args.CurrentAction()?),
+                elapsed: Duration::from_secs(args.ElapsedTime()?.max(0) as u64),
+                start_lba,
+                sector_count,
+                last_written_lba,
+                percent: sector_percent(start_lba, sector_count, last_written_lba),
+            })
+        }
+    }
+}
+
+/// One progress notification during a raw CD burn.
+#[derive(Clone, Copy, Debug)]
+pub struct RawCdProgress {
+    pub phase: RawCdPhase,
+    pub elapsed: Duration,
+    pub start_lba: i32,
+    pub sector_count: i32,
+    pub last_written_lba: i32,
+    /// Disc completion in percent, `None` while the sector range is still
+    /// unknown.
+    pub percent: Option<f64>,
+}
+
+impl RawCdProgress {
+    pub(crate) fn from_event_args(
+        args: &IDiscFormat2RawCDEventArgs,
+    ) -> Result<RawCdProgress, BurnError> {
+        unsafe {
+            let start_lba = args.StartLba()?;
+            let sector_count = args.SectorCount()?;
+            let last_written_lba = args.LastWrittenLba()?;
+            Ok(RawCdProgress {
+                phase: RawCdPhase::from(args.CurrentAction()?),
+                elapsed: Duration::from_secs(args.ElapsedTime()?.max(0) as u64),
+                start_lba,
+                sector_count,
+                last_written_lba,
+                percent: sector_percent(start_lba, sector_count, last_written_lba),
+            })
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Raw (disc-at-once) CD mastering through `IDiscFormat2RawCD`.
+
+use crate::error::BurnError;
+use crate::safearray::read_safearray_i32;
+use windows::Win32::Storage::Imapi::{
+    IDiscFormat2RawCD, IMAPI_FORMAT2_RAW_CD_DATA_SECTOR_TYPE,
+    IMAPI_FORMAT2_RAW_CD_SUBCODE_IS_COOKED, IMAPI_FORMAT2_RAW_CD_SUBCODE_IS_RAW,
+    IMAPI_FORMAT2_RAW_CD_SUBCODE_PQ_ONLY,
+};
+use windows::Win32::System::Com::IStream;
+
+/// Friendly spelling of `IMAPI_FORMAT2_RAW_CD_DATA_SECTOR_TYPE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RawCdSectorType {
+    /// 2352-byte sectors plus P and Q subcode channels.
+    PqOnly,
+    /// 2352-byte sectors plus all subcode channels, cooked.
+    CookedSubcode,
+    /// 2352-byte sectors plus all subcode channels, raw interleaved.
+    RawSubcode,
+}
+
+impl From<RawCdSectorType> for IMAPI_FORMAT2_RAW_CD_DATA_SECTOR_TYPE {
+    fn from(value: RawCdSectorType) -> Self {
+        match value {
+            RawCdSectorType::PqOnly => IMAPI_FORMAT2_RAW_CD_SUBCODE_PQ_ONLY,
+            RawCdSectorType::CookedSubcode => IMAPI_FORMAT2_RAW_CD_SUBCODE_IS_COOKED,
+            RawCdSectorType::RawSubcode => IMAPI_FORMAT2_RAW_CD_SUBCODE_IS_RAW,
+        }
+    }
+}
+
+impl From<IMAPI_FORMAT2_RAW_CD_DATA_SECTOR_TYPE> for RawCdSectorType {
+    fn from(value: IMAPI_FORMAT2_RAW_CD_DATA_SECTOR_TYPE) -> Self {
+        match value {
+            IMAPI_FORMAT2_RAW_CD_SUBCODE_IS_COOKED => RawCdSectorType::CookedSubcode,
+            IMAPI_FORMAT2_RAW_CD_SUBCODE_IS_RAW => RawCdSectorType::RawSubcode,
+            _ => RawCdSectorType::PqOnly,
+        }
+    }
+}
+
+/// Low-level raw CD writer pairing `SetRequestedSectorType` with the
+/// drive's `SupportedSectorTypes` list.
+pub struct RawCdWriter {
+    writer: IDiscFormat2RawCD,
+}
+
+impl RawCdWriter {
+    /// Wraps a raw CD writer that already has a recorder and client name
+    /// assigned.
+    pub fn new(writer: IDiscFormat2RawCD) -> Self {
+        RawCdWriter { writer }
+    }
+
+    /// The sector types the current drive/media pair can accept.
+    pub fn supported_sector_types(&self) -> Result<Vec<RawCdSectorType>, BurnError> {
+        let psa = unsafe { self.writer.SupportedSectorTypes()? };
+        Ok(read_safearray_i32(psa)?
+            .into_iter()
+            .map(|raw| IMAPI_FORMAT2_RAW_CD_DATA_SECTOR_TYPE(raw).into())
+            .collect())
+    }
+
+    /// Selects the sector type of the image the caller will provide,
+    /// rejecting types the drive does not support.
+    pub fn set_sector_type(&self, sector_type: RawCdSectorType) -> Result<(), BurnError> {
+        if !self.supported_sector_types()?.contains(&sector_type) {
+            return Err(BurnError::Unsupported(
+                "the drive does not support the requested raw sector type",
+            ));
+        }
+        unsafe { self.writer.SetRequestedSectorType(sector_type.into())? };
+        Ok(())
+    }
+
+    /// Writes a complete disc-at-once image, prefixed with `lead_in_sectors`
+    /// sectors of lead-in contained in the stream.
+    pub fn write(&self, image: &IStream, lead_in_sectors: i32) -> Result<(), BurnError> {
+        unsafe {
+            self.writer.PrepareMedia()?;
+            let written = self.writer.WriteMedia2(image, lead_in_sectors);
+            // The media must be released even when the write failed, or the
+            // drive stays locked for exclusive raw access.
+            let released = self.writer.ReleaseMedia();
+            written?;
+            released?;
+        }
+        Ok(())
+    }
+
+    /// Like `write`, validating `lead_in_sectors` against the media layout
+    /// before touching the drive: the next session must still fit in front
+    /// of the last possible lead-out, and on a disc that already has
+    /// sessions the lead-in must fit in the writable gap before
+    /// `StartOfNextSession` instead of running back over the previous
+    /// session. Needed for correct multisession raw authoring, where a
+    /// mis-sized lead-in corrupts the existing content.
+    pub fn write_media_with_lead_in(
+        &self,
+        image: &IStream,
+        lead_in_sectors: i32,
+    ) -> Result<(), BurnError> {
+        if lead_in_sectors < 0 {
+            return Err(BurnError::Unsupported(
+                "the lead-in sector count cannot be negative",
+            ));
+        }
+        let next_session = unsafe { self.writer.StartOfNextSession()? };
+        let leadout = unsafe { self.writer.LastPossibleStartOfLeadout()? };
+        if next_session >= leadout {
+            return Err(BurnError::Unsupported(
+                "the media has no room for another session",
+            ));
+        }
+        // On blank media the next session starts at a negative LBA that
+        // already accounts for the standard lead-in; with existing sessions
+        // the writable gap in front of the session is at most
+        // `next_session` sectors deep.
+        if next_session > 0 && lead_in_sectors > next_session {
+            return Err(BurnError::Unsupported(
+                "the requested lead-in would overlap an existing session",
+            ));
+        }
+        self.write(image, lead_in_sectors)
+    }
+}
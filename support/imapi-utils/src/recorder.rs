@@ -0,0 +1,525 @@
+//! Recorder identification helpers.
+
+use crate::com::ComApartment;
+use crate::error::BurnError;
+use crate::safearray::{read_safearray_bstr, read_safearray_i32};
+use crate::util::{bstr_to_string, string_to_bstr};
+use std::fmt;
+use std::sync::mpsc;
+use std::time::Duration;
+use windows::core::{AgileReference, ComInterface};
+use windows::Win32::Foundation::{BOOLEAN, VARIANT_BOOL};
+use windows::Win32::Storage::Imapi::{
+    IDiscRecorder2, IDiscRecorder2Ex, IMAPI_FEATURE_PAGE_TYPE, IMAPI_PROFILE_TYPE,
+    IMAPI_PROFILE_TYPE_BD_REWRITABLE, IMAPI_PROFILE_TYPE_BD_ROM,
+    IMAPI_PROFILE_TYPE_BD_R_RANDOM_RECORDING, IMAPI_PROFILE_TYPE_BD_R_SEQUENTIAL,
+    IMAPI_PROFILE_TYPE_CDROM, IMAPI_PROFILE_TYPE_CD_RECORDABLE, IMAPI_PROFILE_TYPE_CD_REWRITABLE,
+    IMAPI_PROFILE_TYPE_DVDROM, IMAPI_PROFILE_TYPE_DVD_DASH_RECORDABLE,
+    IMAPI_PROFILE_TYPE_DVD_DASH_REWRITABLE, IMAPI_PROFILE_TYPE_DVD_DASH_RW_SEQUENTIAL,
+    IMAPI_PROFILE_TYPE_DVD_DASH_R_DUAL_LAYER_JUMP, IMAPI_PROFILE_TYPE_DVD_DASH_R_DUAL_SEQUENTIAL,
+    IMAPI_PROFILE_TYPE_DVD_PLUS_R, IMAPI_PROFILE_TYPE_DVD_PLUS_RW,
+    IMAPI_PROFILE_TYPE_DVD_PLUS_RW_DUAL, IMAPI_PROFILE_TYPE_DVD_PLUS_R_DUAL,
+    IMAPI_PROFILE_TYPE_DVD_RAM, IMAPI_PROFILE_TYPE_HD_DVD_RAM,
+    IMAPI_PROFILE_TYPE_HD_DVD_RECORDABLE, IMAPI_PROFILE_TYPE_HD_DVD_ROM,
+};
+use windows::Win32::System::Com::CoTaskMemFree;
+
+// Byte offset of `SerialNumberOffset` within `STORAGE_DEVICE_DESCRIPTOR`.
+const SERIAL_NUMBER_OFFSET_FIELD: usize = 24;
+
+// Extracts the serial number string from a raw `STORAGE_DEVICE_DESCRIPTOR`
+// blob. Devices without one report an offset of zero.
+fn serial_from_descriptor(descriptor: &[u8]) -> Option<String> {
+    let field = descriptor.get(SERIAL_NUMBER_OFFSET_FIELD..SERIAL_NUMBER_OFFSET_FIELD + 4)?;
+    let offset = u32::from_le_bytes([field[0], field[1], field[2], field[3]]) as usize;
+    if offset == 0 || offset >= descriptor.len() {
+        return None;
+    }
+    let tail = &descriptor[offset..];
+    let end = tail
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(tail.len());
+    let serial = String::from_utf8_lossy(&tail[..end]).trim().to_string();
+    if serial.is_empty() {
+        None
+    } else {
+        Some(serial)
+    }
+}
+
+/// Serial number of the device behind `recorder`, parsed from the storage
+/// device descriptor. `Ok(None)` when the device simply doesn't report one,
+/// which is common for older drives.
+pub fn serial_number(recorder: &IDiscRecorder2Ex) -> Result<Option<String>, BurnError> {
+    let descriptor = unsafe {
+        let mut ptr: *mut u8 = std::ptr::null_mut();
+        let mut size = 0u32;
+        recorder.GetDeviceDescriptor(&mut ptr, &mut size)?;
+        let data = std::slice::from_raw_parts(ptr, size as usize).to_vec();
+        CoTaskMemFree(Some(ptr as *const _));
+        data
+    };
+    Ok(serial_from_descriptor(&descriptor))
+}
+
+/// Identity of a recorder, with enough detail to tell apart two attached
+/// drives of the same model.
+#[derive(Clone, Debug)]
+pub struct RecorderInfo {
+    pub vendor_id: String,
+    pub product_id: String,
+    pub product_revision: String,
+    /// `None` when the device doesn't report a serial number.
+    pub serial_number: Option<String>,
+}
+
+impl RecorderInfo {
+    /// Queries the identity of the drive behind `recorder`.
+    pub fn query(recorder: &IDiscRecorder2Ex) -> Result<RecorderInfo, BurnError> {
+        let plain: IDiscRecorder2 = recorder.cast()?;
+        unsafe {
+            Ok(RecorderInfo {
+                vendor_id: bstr_to_string(&plain.VendorId()?).trim().to_string(),
+                product_id: bstr_to_string(&plain.ProductId()?).trim().to_string(),
+                product_revision: bstr_to_string(&plain.ProductRevision()?).trim().to_string(),
+                serial_number: serial_number(recorder)?,
+            })
+        }
+    }
+}
+
+impl fmt::Display for RecorderInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.vendor_id, self.product_id, self.product_revision
+        )?;
+        if let Some(serial) = &self.serial_number {
+            write!(f, " (s/n {})", serial)?;
+        }
+        Ok(())
+    }
+}
+
+/// A drive feature profile, mirroring the common `IMAPI_PROFILE_TYPE`
+/// values. Profiles this build doesn't know about are preserved as
+/// `Unknown`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Profile {
+    CdRom,
+    CdRecordable,
+    CdRewritable,
+    DvdRom,
+    DvdDashRecordable,
+    DvdRam,
+    DvdDashRewritable,
+    DvdDashRwSequential,
+    DvdDashRDualSequential,
+    DvdDashRDualLayerJump,
+    DvdPlusRw,
+    DvdPlusR,
+    DvdPlusRwDual,
+    DvdPlusRDual,
+    BdRom,
+    BdRSequential,
+    BdRRandomRecording,
+    BdRewritable,
+    HdDvdRom,
+    HdDvdRecordable,
+    HdDvdRam,
+    Unknown(i32),
+}
+
+impl From<IMAPI_PROFILE_TYPE> for Profile {
+    fn from(value: IMAPI_PROFILE_TYPE) -> Self {
+        match value {
+            IMAPI_PROFILE_TYPE_CDROM => Profile::CdRom,
+            IMAPI_PROFILE_TYPE_CD_RECORDABLE => Profile::CdRecordable,
+            IMAPI_PROFILE_TYPE_CD_REWRITABLE => Profile::CdRewritable,
+            IMAPI_PROFILE_TYPE_DVDROM => Profile::DvdRom,
+            IMAPI_PROFILE_TYPE_DVD_DASH_RECORDABLE => Profile::DvdDashRecordable,
+            IMAPI_PROFILE_TYPE_DVD_RAM => Profile::DvdRam,
+            IMAPI_PROFILE_TYPE_DVD_DASH_REWRITABLE => Profile::DvdDashRewritable,
+            IMAPI_PROFILE_TYPE_DVD_DASH_RW_SEQUENTIAL => Profile::DvdDashRwSequential,
+            IMAPI_PROFILE_TYPE_DVD_DASH_R_DUAL_SEQUENTIAL => Profile::DvdDashRDualSequential,
+            IMAPI_PROFILE_TYPE_DVD_DASH_R_DUAL_LAYER_JUMP => Profile::DvdDashRDualLayerJump,
+            IMAPI_PROFILE_TYPE_DVD_PLUS_RW => Profile::DvdPlusRw,
+            IMAPI_PROFILE_TYPE_DVD_PLUS_R => Profile::DvdPlusR,
+            IMAPI_PROFILE_TYPE_DVD_PLUS_RW_DUAL => Profile::DvdPlusRwDual,
+            IMAPI_PROFILE_TYPE_DVD_PLUS_R_DUAL => Profile::DvdPlusRDual,
+            IMAPI_PROFILE_TYPE_BD_ROM => Profile::BdRom,
+            IMAPI_PROFILE_TYPE_BD_R_SEQUENTIAL => Profile::BdRSequential,
+            IMAPI_PROFILE_TYPE_BD_R_RANDOM_RECORDING => Profile::BdRRandomRecording,
+            IMAPI_PROFILE_TYPE_BD_REWRITABLE => Profile::BdRewritable,
+            IMAPI_PROFILE_TYPE_HD_DVD_ROM => Profile::HdDvdRom,
+            IMAPI_PROFILE_TYPE_HD_DVD_RECORDABLE => Profile::HdDvdRecordable,
+            IMAPI_PROFILE_TYPE_HD_DVD_RAM => Profile::HdDvdRam,
+            other => Profile::Unknown(other.0),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Profile::CdRom => f.write_str("CD-ROM"),
+            Profile::CdRecordable => f.write_str("CD-R"),
+            Profile::CdRewritable => f.write_str("CD-RW"),
+            Profile::DvdRom => f.write_str("DVD-ROM"),
+            Profile::DvdDashRecordable => f.write_str("DVD-R"),
+            Profile::DvdRam => f.write_str("DVD-RAM"),
+            Profile::DvdDashRewritable => f.write_str("DVD-RW RO"),
+            Profile::DvdDashRwSequential => f.write_str("DVD-RW SR"),
+            Profile::DvdDashRDualSequential => f.write_str("DVD-R DL SR"),
+            Profile::DvdDashRDualLayerJump => f.write_str("DVD-R DL LJ"),
+            Profile::DvdPlusRw => f.write_str("DVD+RW"),
+            Profile::DvdPlusR => f.write_str("DVD+R"),
+            Profile::DvdPlusRwDual => f.write_str("DVD+RW DL"),
+            Profile::DvdPlusRDual => f.write_str("DVD+R DL"),
+            Profile::BdRom => f.write_str("BD-ROM"),
+            Profile::BdRSequential => f.write_str("BD-R SRM"),
+            Profile::BdRRandomRecording => f.write_str("BD-R RRM"),
+            Profile::BdRewritable => f.write_str("BD-RE"),
+            Profile::HdDvdRom => f.write_str("HD DVD-ROM"),
+            Profile::HdDvdRecordable => f.write_str("HD DVD-R"),
+            Profile::HdDvdRam => f.write_str("HD DVD-RAM"),
+            Profile::Unknown(raw) => write!(f, "profile {:#06x}", raw),
+        }
+    }
+}
+
+/// Profiles the drive supports, or with `current_only` just the ones active
+/// for the loaded media.
+///
+/// This is the lower-level `IDiscRecorder2Ex` path, complementing the
+/// SAFEARRAY-based `SupportedProfiles` on `IDiscRecorder2`. The out buffer
+/// is IMAPI-allocated through the COM task allocator, so it's copied into
+/// the `Vec` and released with `CoTaskMemFree` — never with Rust's
+/// allocator.
+pub fn supported_profile_types(
+    recorder: &IDiscRecorder2Ex,
+    current_only: bool,
+) -> Result<Vec<Profile>, BurnError> {
+    let raw = unsafe {
+        let mut ptr: *mut IMAPI_PROFILE_TYPE = std::ptr::null_mut();
+        let mut count = 0u32;
+        recorder.GetSupportedProfiles(BOOLEAN::from(current_only), &mut ptr, &mut count)?;
+        let values = std::slice::from_raw_parts(ptr, count as usize).to_vec();
+        CoTaskMemFree(Some(ptr as *const _));
+        values
+    };
+    Ok(raw.into_iter().map(Profile::from).collect())
+}
+
+// MMC feature names for the codes `GetSupportedFeaturePages` reports, so a
+// drive's feature set reads as prose instead of hex.
+const FEATURE_PAGE_NAMES: &[(i32, &str)] = &[
+    (0x0000, "Profile List"),
+    (0x0001, "Core"),
+    (0x0002, "Morphing"),
+    (0x0003, "Removable Medium"),
+    (0x0004, "Write Protect"),
+    (0x0010, "Random Readable"),
+    (0x001d, "Multi-Read"),
+    (0x001e, "CD Read"),
+    (0x001f, "DVD Read"),
+    (0x0020, "Random Writable"),
+    (0x0021, "Incremental Streaming Writable"),
+    (0x0022, "Sector Erasable"),
+    (0x0023, "Formattable"),
+    (0x0024, "Hardware Defect Management"),
+    (0x0025, "Write Once"),
+    (0x0026, "Restricted Overwrite"),
+    (0x0027, "CD-RW CAV Write"),
+    (0x0028, "MRW"),
+    (0x0029, "Enhanced Defect Reporting"),
+    (0x002a, "DVD+RW"),
+    (0x002b, "DVD+R"),
+    (0x002c, "Rigid Restricted Overwrite"),
+    (0x002d, "CD Track at Once"),
+    (0x002e, "CD Mastering"),
+    (0x002f, "DVD-R/-RW Write"),
+    (0x0033, "Layer Jump Recording"),
+    (0x0037, "CD-RW Media Write Support"),
+    (0x0038, "BD-R Pseudo-Overwrite"),
+    (0x003a, "DVD+RW Dual Layer"),
+    (0x003b, "DVD+R Dual Layer"),
+    (0x0040, "BD Read"),
+    (0x0041, "BD Write"),
+    (0x0042, "TSR"),
+    (0x0050, "HD DVD Read"),
+    (0x0051, "HD DVD Write"),
+    (0x0080, "Hybrid Disc"),
+    (0x0100, "Power Management"),
+    (0x0101, "SMART"),
+    (0x0102, "Embedded Changer"),
+    (0x0103, "CD Audio External Play"),
+    (0x0104, "Microcode Upgrade"),
+    (0x0105, "Timeout"),
+    (0x0106, "DVD CSS"),
+    (0x0107, "Real Time Streaming"),
+    (0x0108, "Logical Unit Serial Number"),
+    (0x0109, "Media Serial Number"),
+    (0x010a, "Disc Control Blocks"),
+    (0x010b, "DVD CPRM"),
+    (0x010c, "Firmware Information"),
+];
+
+/// The MMC name of a feature page, or `None` for codes this table doesn't
+/// know. The orphan rule keeps us from implementing `Display` on the
+/// generated `IMAPI_FEATURE_PAGE_TYPE` itself.
+pub fn feature_page_name(page: IMAPI_FEATURE_PAGE_TYPE) -> Option<&'static str> {
+    FEATURE_PAGE_NAMES
+        .iter()
+        .find(|(code, _)| *code == page.0)
+        .map(|(_, name)| *name)
+}
+
+/// Feature pages the drive supports, or with `current_only` just the ones
+/// active for the loaded media.
+///
+/// `GetSupportedFeaturePages` reports the out buffer in bytes; a size that
+/// isn't a whole number of entries means the device response is garbage and
+/// is rejected rather than truncated. The buffer is IMAPI-allocated through
+/// the COM task allocator, so it's copied and released with `CoTaskMemFree`.
+pub fn supported_feature_pages(
+    recorder: &IDiscRecorder2Ex,
+    current_only: bool,
+) -> Result<Vec<IMAPI_FEATURE_PAGE_TYPE>, BurnError> {
+    const ENTRY_SIZE: usize = std::mem::size_of::<IMAPI_FEATURE_PAGE_TYPE>();
+    unsafe {
+        let mut ptr: *mut IMAPI_FEATURE_PAGE_TYPE = std::ptr::null_mut();
+        let mut byte_size = 0u32;
+        recorder.GetSupportedFeaturePages(BOOLEAN::from(current_only), &mut ptr, &mut byte_size)?;
+        if byte_size as usize % ENTRY_SIZE != 0 {
+            CoTaskMemFree(Some(ptr as *const _));
+            return Err(BurnError::MalformedResponse(
+                "feature page list is not a whole number of entries",
+            ));
+        }
+        let values = std::slice::from_raw_parts(ptr, byte_size as usize / ENTRY_SIZE).to_vec();
+        CoTaskMemFree(Some(ptr as *const _));
+        Ok(values)
+    }
+}
+
+/// The full capability set of a drive: profiles, feature pages and mode
+/// pages in one query, for diagnostics dumps.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecorderCapabilities {
+    /// Every profile the drive supports.
+    pub supported_profiles: Vec<Profile>,
+    /// Profiles active for the currently loaded media.
+    pub current_profiles: Vec<Profile>,
+    pub supported_feature_pages: Vec<i32>,
+    pub supported_mode_pages: Vec<i32>,
+    /// Whether the drive can load media through `EjectMedia`'s counterpart.
+    pub can_load_media: bool,
+    /// The legacy `IDiscMaster` device number, for tools that still index
+    /// drives that way.
+    pub legacy_device_number: i32,
+}
+
+/// Queries the complete capability set of `recorder`.
+pub fn capabilities(recorder: &IDiscRecorder2) -> Result<RecorderCapabilities, BurnError> {
+    unsafe {
+        let supported_profiles = read_safearray_i32(recorder.SupportedProfiles()?)?
+            .into_iter()
+            .map(|raw| Profile::from(IMAPI_PROFILE_TYPE(raw)))
+            .collect();
+        let current_profiles = read_safearray_i32(recorder.CurrentProfiles()?)?
+            .into_iter()
+            .map(|raw| Profile::from(IMAPI_PROFILE_TYPE(raw)))
+            .collect();
+        Ok(RecorderCapabilities {
+            supported_profiles,
+            current_profiles,
+            supported_feature_pages: read_safearray_i32(recorder.SupportedFeaturePages()?)?,
+            supported_mode_pages: read_safearray_i32(recorder.SupportedModePages()?)?,
+            can_load_media: recorder.DeviceCanLoadMedia()?.as_bool(),
+            legacy_device_number: recorder.LegacyDeviceNumber()?,
+        })
+    }
+}
+
+/// Mount points of the drive (e.g. `D:\`), decoded from the BSTR
+/// SAFEARRAY `VolumePathNames` returns.
+pub fn volume_path_names(recorder: &IDiscRecorder2) -> Result<Vec<String>, BurnError> {
+    Ok(read_safearray_bstr(unsafe { recorder.VolumePathNames()? })?)
+}
+
+/// RAII guard holding exclusive access to a recorder, releasing it on drop
+/// so a panic or early return can't leave the drive wedged behind a leaked
+/// lock.
+pub struct ExclusiveAccess {
+    recorder: IDiscRecorder2,
+}
+
+/// Acquires exclusive access to `recorder` under `client` as the visible
+/// owner name. `force` steals the lock from a defunct owner.
+pub fn acquire_exclusive(
+    recorder: &IDiscRecorder2,
+    force: bool,
+    client: &str,
+) -> Result<ExclusiveAccess, BurnError> {
+    unsafe { recorder.AcquireExclusiveAccess(VARIANT_BOOL::from(force), &string_to_bstr(client))? };
+    Ok(ExclusiveAccess {
+        recorder: recorder.clone(),
+    })
+}
+
+impl ExclusiveAccess {
+    /// The client name currently holding the lock.
+    pub fn owner(&self) -> Result<String, BurnError> {
+        Ok(bstr_to_string(&unsafe {
+            self.recorder.ExclusiveAccessOwner()?
+        }))
+    }
+}
+
+impl Drop for ExclusiveAccess {
+    fn drop(&mut self) {
+        if let Err(err) = unsafe { self.recorder.ReleaseExclusiveAccess() } {
+            log::warn!("releasing exclusive drive access failed: {}", err);
+        }
+    }
+}
+
+/// RAII guard keeping media-change notifications (MCN) disabled, so the
+/// shell doesn't react to the disc while a burn is in flight. Re-enables
+/// MCN on drop.
+///
+/// While the guard lives, autorun and the explorer media prompts stay
+/// silent for this drive. IMAPI counts `DisableMcn` calls, so nested guards
+/// balance correctly and notifications resume with the last drop.
+pub struct McnSuppressed {
+    recorder: IDiscRecorder2,
+}
+
+/// Disables media-change notifications on `recorder` until the returned
+/// guard is dropped.
+pub fn suppress_mcn(recorder: &IDiscRecorder2) -> Result<McnSuppressed, BurnError> {
+    unsafe { recorder.DisableMcn()? };
+    Ok(McnSuppressed {
+        recorder: recorder.clone(),
+    })
+}
+
+impl Drop for McnSuppressed {
+    fn drop(&mut self) {
+        if let Err(err) = unsafe { self.recorder.EnableMcn() } {
+            log::warn!("re-enabling media change notifications failed: {}", err);
+        }
+    }
+}
+
+// Runs a blocking recorder call on a worker thread so the caller can bail
+// out after `timeout`. The interface crosses the apartment boundary through
+// an `AgileReference`, which handles the marshaling; the worker enters its
+// own apartment before resolving it. A hung call leaves the worker thread
+// blocked in the drive, which is exactly the condition the timeout papers
+// over.
+fn recorder_call_with_timeout(
+    recorder: &IDiscRecorder2,
+    timeout: Duration,
+    call: fn(&IDiscRecorder2) -> windows::core::Result<()>,
+) -> Result<(), BurnError> {
+    let agile = AgileReference::new(recorder)?;
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let result = (|| -> Result<(), BurnError> {
+            let _com = ComApartment::enter()?;
+            let recorder = agile.resolve()?;
+            call(&recorder).map_err(BurnError::from)
+        })();
+        let _ = sender.send(result);
+    });
+    receiver
+        .recv_timeout(timeout)
+        .unwrap_or(Err(BurnError::Timeout))
+}
+
+/// Ejects the media, failing with `BurnError::Timeout` when the drive
+/// doesn't respond in time.
+pub fn eject_with_timeout(recorder: &IDiscRecorder2, timeout: Duration) -> Result<(), BurnError> {
+    recorder_call_with_timeout(recorder, timeout, |recorder| unsafe {
+        recorder.EjectMedia()
+    })
+}
+
+/// Closes the tray, failing with `BurnError::Timeout` when the drive
+/// doesn't respond in time.
+pub fn close_tray_with_timeout(
+    recorder: &IDiscRecorder2,
+    timeout: Duration,
+) -> Result<(), BurnError> {
+    recorder_call_with_timeout(recorder, timeout, |recorder| unsafe {
+        recorder.CloseTray()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exclusive_guard_releases_on_drop() {
+        use crate::mock::MockRecorderBuilder;
+
+        let _com = ComApartment::enter().unwrap();
+        let (recorder, calls) = MockRecorderBuilder::new().build();
+        {
+            let _guard = acquire_exclusive(&recorder, false, "imapi-utils tests").unwrap();
+            assert_eq!(calls.exclusive_acquires(), 1);
+            assert_eq!(calls.exclusive_releases(), 0);
+        }
+        assert_eq!(calls.exclusive_releases(), 1);
+    }
+
+    #[test]
+    fn mcn_guard_rebalances_on_drop() {
+        use crate::mock::MockRecorderBuilder;
+
+        let _com = ComApartment::enter().unwrap();
+        let (recorder, calls) = MockRecorderBuilder::new().build();
+        {
+            let _quiet = suppress_mcn(&recorder).unwrap();
+            assert_eq!(calls.mcn_disables(), 1);
+            assert_eq!(calls.mcn_enables(), 0);
+        }
+        assert_eq!(calls.mcn_enables(), 1);
+    }
+
+    #[test]
+    fn profile_names() {
+        assert_eq!(Profile::DvdPlusRDual.to_string(), "DVD+R DL");
+        assert_eq!(Profile::BdRSequential.to_string(), "BD-R SRM");
+        assert_eq!(Profile::Unknown(0x5a).to_string(), "profile 0x005a");
+    }
+
+    #[test]
+    fn serial_parsing() {
+        // Header with SerialNumberOffset pointing at the trailing string.
+        let mut descriptor = vec![0u8; 28];
+        descriptor[SERIAL_NUMBER_OFFSET_FIELD] = 28;
+        descriptor.extend_from_slice(b"ABC123\0");
+        assert_eq!(
+            serial_from_descriptor(&descriptor),
+            Some("ABC123".to_string())
+        );
+
+        // Offset zero means no serial.
+        let empty = vec![0u8; 32];
+        assert_eq!(serial_from_descriptor(&empty), None);
+
+        // A whitespace-only serial is as good as none.
+        let mut blank = vec![0u8; 28];
+        blank[SERIAL_NUMBER_OFFSET_FIELD] = 28;
+        blank.extend_from_slice(b"   \0");
+        assert_eq!(serial_from_descriptor(&blank), None);
+    }
+}
@@ -0,0 +1,163 @@
+//! Human readable capability dumps for drive-specific bug reports.
+
+use crate::error::BurnError;
+use crate::scsi::get_mode_page;
+use std::fmt::Write;
+use windows::Win32::Foundation::BOOLEAN;
+use windows::Win32::Storage::Imapi::{
+    IDiscRecorder2Ex, IMAPI_FEATURE_PAGE_TYPE, IMAPI_MODE_PAGE_REQUEST_TYPE_CURRENT_VALUES,
+    IMAPI_MODE_PAGE_TYPE, IMAPI_PROFILE_TYPE,
+};
+use windows::Win32::System::Com::CoTaskMemFree;
+
+// Renders `data` as a classic offset + hex dump, 16 bytes per line.
+fn hex_dump(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (index, line) in data.chunks(16).enumerate() {
+        let _ = write!(out, "  {:04x}:", index * 16);
+        for byte in line {
+            let _ = write!(out, " {:02x}", byte);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// Copies a CoTaskMem allocated list handed out by the recorder into a Vec,
+// freeing the allocation.
+unsafe fn take_list<T: Copy>(ptr: *mut T, count: u32) -> Vec<T> {
+    let values = std::slice::from_raw_parts(ptr, count as usize).to_vec();
+    CoTaskMemFree(Some(ptr as *const _));
+    values
+}
+
+// One report section: either the rendered content or a note about the
+// failure, so a single misbehaving query doesn't lose the whole dump.
+fn section(out: &mut String, title: &str, content: Result<String, BurnError>) {
+    let _ = writeln!(out, "== {} ==", title);
+    match content {
+        Ok(content) => out.push_str(&content),
+        Err(err) => {
+            let _ = writeln!(out, "  error: {}", err);
+        }
+    }
+    out.push('\n');
+}
+
+/// Collects supported profiles, feature pages, mode page dumps and the
+/// device/adapter descriptors of `recorder` into a multi-line report meant
+/// to be pasted into bug reports.
+///
+/// Sections are gathered independently: a failing query is recorded as an
+/// error note in its section and the remaining sections are still produced.
+pub fn capability_report(recorder: &IDiscRecorder2Ex) -> Result<String, BurnError> {
+    let mut report = String::new();
+
+    section(&mut report, "Supported profiles", unsafe {
+        let mut ptr: *mut IMAPI_PROFILE_TYPE = std::ptr::null_mut();
+        let mut count = 0u32;
+        recorder
+            .GetSupportedProfiles(BOOLEAN::from(false), &mut ptr, &mut count)
+            .map_err(BurnError::from)
+            .map(|()| {
+                take_list(ptr, count)
+                    .into_iter()
+                    .map(|profile| format!("  0x{:04x}\n", profile.0))
+                    .collect()
+            })
+    });
+
+    section(&mut report, "Supported feature pages", unsafe {
+        let mut ptr: *mut IMAPI_FEATURE_PAGE_TYPE = std::ptr::null_mut();
+        let mut count = 0u32;
+        recorder
+            .GetSupportedFeaturePages(BOOLEAN::from(false), &mut ptr, &mut count)
+            .map_err(BurnError::from)
+            .map(|()| {
+                take_list(ptr, count)
+                    .into_iter()
+                    .map(|page| format!("  0x{:04x}\n", page.0))
+                    .collect()
+            })
+    });
+
+    // Mode pages get a full current-values dump each, since those bytes are
+    // usually what a drive-specific investigation needs.
+    let mode_pages = unsafe {
+        let mut ptr: *mut IMAPI_MODE_PAGE_TYPE = std::ptr::null_mut();
+        let mut count = 0u32;
+        recorder
+            .GetSupportedModePages(
+                IMAPI_MODE_PAGE_REQUEST_TYPE_CURRENT_VALUES,
+                &mut ptr,
+                &mut count,
+            )
+            .map_err(BurnError::from)
+            .map(|()| take_list(ptr, count))
+    };
+    match mode_pages {
+        Ok(pages) => {
+            for page in pages {
+                section(
+                    &mut report,
+                    &format!("Mode page 0x{:02x}", page.0),
+                    get_mode_page(recorder, page, IMAPI_MODE_PAGE_REQUEST_TYPE_CURRENT_VALUES)
+                        .map(|data| hex_dump(&data)),
+                );
+            }
+        }
+        Err(err) => section(&mut report, "Mode pages", Err(err)),
+    }
+
+    section(&mut report, "Device descriptor", unsafe {
+        let mut ptr: *mut u8 = std::ptr::null_mut();
+        let mut size = 0u32;
+        recorder
+            .GetDeviceDescriptor(&mut ptr, &mut size)
+            .map_err(BurnError::from)
+            .map(|()| {
+                let data = std::slice::from_raw_parts(ptr, size as usize).to_vec();
+                CoTaskMemFree(Some(ptr as *const _));
+                hex_dump(&data)
+            })
+    });
+
+    section(&mut report, "Adapter descriptor", unsafe {
+        let mut ptr: *mut u8 = std::ptr::null_mut();
+        let mut size = 0u32;
+        recorder
+            .GetAdapterDescriptor(&mut ptr, &mut size)
+            .map_err(BurnError::from)
+            .map(|()| {
+                let data = std::slice::from_raw_parts(ptr, size as usize).to_vec();
+                CoTaskMemFree(Some(ptr as *const _));
+                hex_dump(&data)
+            })
+    });
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hex_dump_layout() {
+        let dump = hex_dump(&[0x00, 0x01, 0xff]);
+        assert_eq!(dump, "  0000: 00 01 ff\n");
+
+        let two_lines = hex_dump(&[0u8; 17]);
+        assert!(two_lines.contains("  0010: 00\n"));
+    }
+
+    #[test]
+    fn failing_section_keeps_the_report() {
+        let mut report = String::new();
+        section(&mut report, "Broken", Err(BurnError::CapacityNotSet));
+        section(&mut report, "Fine", Ok("  content\n".to_string()));
+        assert!(report.contains("== Broken =="));
+        assert!(report.contains("error:"));
+        assert!(report.contains("  content"));
+    }
+}
@@ -0,0 +1,194 @@
+//! Minimal SAFEARRAY decoding helpers shared by the property wrappers.
+
+use crate::events::{variant_to_bstr, variant_to_dispatch, variant_to_i32};
+use crate::util::bstr_to_string;
+use std::mem::ManuallyDrop;
+use windows::core::{Error, Result, BSTR};
+use windows::Win32::Foundation::E_OUTOFMEMORY;
+use windows::Win32::System::Com::{IDispatch, SAFEARRAY};
+use windows::Win32::System::Ole::{
+    SafeArrayAccessData, SafeArrayCreateVector, SafeArrayDestroy, SafeArrayGetLBound,
+    SafeArrayGetUBound, SafeArrayGetVartype, SafeArrayPutElement, SafeArrayUnaccessData,
+};
+use windows::Win32::System::Variant::{
+    VARIANT, VARIANT_0, VARIANT_0_0, VARIANT_0_0_0, VT_BSTR, VT_DISPATCH, VT_I4, VT_UI4,
+    VT_UNKNOWN, VT_VARIANT,
+};
+
+/// Decodes a one dimensional SAFEARRAY of `VT_I4` values (or of `VARIANT`s
+/// holding one) into a vector. The array is destroyed afterwards since the
+/// IMAPI getters hand its ownership to the caller.
+pub(crate) fn read_safearray_i32(psa: *mut SAFEARRAY) -> Result<Vec<i32>> {
+    if psa.is_null() {
+        return Ok(Vec::new());
+    }
+    unsafe {
+        let decoded = (|| -> Result<Vec<i32>> {
+            let vt = SafeArrayGetVartype(psa)?;
+            let lower = SafeArrayGetLBound(psa, 1)?;
+            let upper = SafeArrayGetUBound(psa, 1)?;
+            if upper < lower {
+                return Ok(Vec::new());
+            }
+            let count = (upper - lower + 1) as usize;
+            let mut data = std::ptr::null_mut();
+            SafeArrayAccessData(psa, &mut data)?;
+            let values = match vt {
+                VT_I4 | VT_UI4 => std::slice::from_raw_parts(data as *const i32, count).to_vec(),
+                VT_VARIANT => std::slice::from_raw_parts(data as *const VARIANT, count)
+                    .iter()
+                    .filter_map(variant_to_i32)
+                    .collect(),
+                _ => Vec::new(),
+            };
+            SafeArrayUnaccessData(psa)?;
+            Ok(values)
+        })();
+        let _ = SafeArrayDestroy(psa);
+        decoded
+    }
+}
+
+/// Decodes a one dimensional SAFEARRAY of dispatch pointers (either raw
+/// `VT_DISPATCH`/`VT_UNKNOWN` elements or `VARIANT`s holding one) into
+/// owned interface pointers. The array is destroyed afterwards.
+pub(crate) fn read_safearray_dispatch(psa: *mut SAFEARRAY) -> Result<Vec<IDispatch>> {
+    if psa.is_null() {
+        return Ok(Vec::new());
+    }
+    unsafe {
+        let decoded = (|| -> Result<Vec<IDispatch>> {
+            let vt = SafeArrayGetVartype(psa)?;
+            let lower = SafeArrayGetLBound(psa, 1)?;
+            let upper = SafeArrayGetUBound(psa, 1)?;
+            if upper < lower {
+                return Ok(Vec::new());
+            }
+            let count = (upper - lower + 1) as usize;
+            let mut data = std::ptr::null_mut();
+            SafeArrayAccessData(psa, &mut data)?;
+            let values = match vt {
+                VT_DISPATCH | VT_UNKNOWN => {
+                    std::slice::from_raw_parts(data as *const Option<IDispatch>, count)
+                        .iter()
+                        .filter_map(|value| value.clone())
+                        .collect()
+                }
+                VT_VARIANT => std::slice::from_raw_parts(data as *const VARIANT, count)
+                    .iter()
+                    .filter_map(variant_to_dispatch)
+                    .collect(),
+                _ => Vec::new(),
+            };
+            SafeArrayUnaccessData(psa)?;
+            Ok(values)
+        })();
+        let _ = SafeArrayDestroy(psa);
+        decoded
+    }
+}
+
+/// Decodes a one dimensional SAFEARRAY of `VT_BSTR` values (or of
+/// `VARIANT`s holding one) into owned strings. The array is destroyed
+/// afterwards.
+pub(crate) fn read_safearray_bstr(psa: *mut SAFEARRAY) -> Result<Vec<String>> {
+    if psa.is_null() {
+        return Ok(Vec::new());
+    }
+    unsafe {
+        let decoded = (|| -> Result<Vec<String>> {
+            let vt = SafeArrayGetVartype(psa)?;
+            let lower = SafeArrayGetLBound(psa, 1)?;
+            let upper = SafeArrayGetUBound(psa, 1)?;
+            if upper < lower {
+                return Ok(Vec::new());
+            }
+            let count = (upper - lower + 1) as usize;
+            let mut data = std::ptr::null_mut();
+            SafeArrayAccessData(psa, &mut data)?;
+            let values = match vt {
+                VT_BSTR => std::slice::from_raw_parts(data as *const BSTR, count)
+                    .iter()
+                    .map(bstr_to_string)
+                    .collect(),
+                VT_VARIANT => std::slice::from_raw_parts(data as *const VARIANT, count)
+                    .iter()
+                    .filter_map(variant_to_bstr)
+                    .map(|value| bstr_to_string(&value))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            SafeArrayUnaccessData(psa)?;
+            Ok(values)
+        })();
+        let _ = SafeArrayDestroy(psa);
+        decoded
+    }
+}
+
+/// Builds a one dimensional SAFEARRAY of `VARIANT`s holding `VT_DISPATCH`
+/// pointers, the layout IMAPI hands out for its interface collections. The
+/// caller owns the returned array and destroys it once the consumer copied
+/// it.
+pub(crate) fn make_safearray_dispatch(items: &[IDispatch]) -> Result<*mut SAFEARRAY> {
+    unsafe {
+        let psa = SafeArrayCreateVector(VT_VARIANT, 0, items.len() as u32);
+        if psa.is_null() {
+            return Err(Error::from(E_OUTOFMEMORY));
+        }
+        let filled = (|| -> Result<()> {
+            for (index, item) in items.iter().enumerate() {
+                let variant = VARIANT {
+                    Anonymous: VARIANT_0 {
+                        Anonymous: ManuallyDrop::new(VARIANT_0_0 {
+                            vt: VT_DISPATCH,
+                            wReserved1: 0,
+                            wReserved2: 0,
+                            wReserved3: 0,
+                            Anonymous: VARIANT_0_0_0 {
+                                pdispVal: ManuallyDrop::new(Some(item.clone())),
+                            },
+                        }),
+                    },
+                };
+                let put = SafeArrayPutElement(
+                    psa,
+                    &(index as i32),
+                    &variant as *const _ as *const std::ffi::c_void,
+                );
+                // `SafeArrayPutElement` copied the variant; release ours.
+                let inner = ManuallyDrop::into_inner(variant.Anonymous.Anonymous);
+                drop(ManuallyDrop::into_inner(inner.Anonymous.pdispVal));
+                put?;
+            }
+            Ok(())
+        })();
+        if let Err(err) = filled {
+            let _ = SafeArrayDestroy(psa);
+            return Err(err);
+        }
+        Ok(psa)
+    }
+}
+
+/// Builds a one dimensional SAFEARRAY of `VT_I4` values. The caller owns
+/// the returned array.
+pub(crate) fn make_safearray_i32(items: &[i32]) -> Result<*mut SAFEARRAY> {
+    unsafe {
+        let psa = SafeArrayCreateVector(VT_I4, 0, items.len() as u32);
+        if psa.is_null() {
+            return Err(Error::from(E_OUTOFMEMORY));
+        }
+        for (index, item) in items.iter().enumerate() {
+            if let Err(err) = SafeArrayPutElement(
+                psa,
+                &(index as i32),
+                item as *const _ as *const std::ffi::c_void,
+            ) {
+                let _ = SafeArrayDestroy(psa);
+                return Err(err);
+            }
+        }
+        Ok(psa)
+    }
+}
@@ -0,0 +1,366 @@
+//! Pass-through SCSI plumbing on top of `IDiscRecorder2Ex`.
+
+use crate::error::BurnError;
+use crate::sense::classify_burn_failure;
+use log::warn;
+use std::time::Duration;
+use windows::Win32::Storage::Imapi::{
+    IDiscRecorder2Ex, IMAPI_MODE_PAGE_REQUEST_TYPE, IMAPI_MODE_PAGE_TYPE,
+    IMAPI_READ_TRACK_ADDRESS_TYPE,
+};
+use windows::Win32::System::Com::CoTaskMemFree;
+
+/// Size of the sense buffer IMAPI expects for pass-through commands.
+pub(crate) const SENSE_BUFFER_SIZE: usize = 18;
+
+/// Number of bytes in a data sector.
+pub(crate) const SECTOR_SIZE: usize = 2048;
+
+// Quick diagnostic commands shouldn't hold a hung drive for long.
+const SHORT_TIMEOUT: Duration = Duration::from_secs(10);
+// Blank/format passes legitimately run for many minutes.
+const LONG_TIMEOUT: Duration = Duration::from_secs(3600);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Sensible default timeout for a CDB opcode.
+fn default_timeout(opcode: u8) -> Duration {
+    match opcode {
+        // TEST UNIT READY, INQUIRY, READ CAPACITY, READ TOC,
+        // GET CONFIGURATION, READ DISC INFORMATION, READ TRACK INFORMATION.
+        0x00 | 0x12 | 0x25 | 0x43 | 0x46 | 0x51 | 0x52 => SHORT_TIMEOUT,
+        // FORMAT UNIT, BLANK.
+        0x04 | 0xa1 => LONG_TIMEOUT,
+        _ => DEFAULT_TIMEOUT,
+    }
+}
+
+// Converts a Duration to the whole seconds IMAPI expects, rounding up so a
+// sub-second timeout never becomes zero, and clamping on overflow.
+fn timeout_to_seconds(timeout: Duration) -> u32 {
+    let mut seconds = timeout.as_secs();
+    if timeout.subsec_nanos() > 0 {
+        seconds += 1;
+    }
+    if seconds > u64::from(u32::MAX) {
+        warn!("SCSI timeout of {:?} clamped to u32::MAX seconds", timeout);
+        return u32::MAX;
+    }
+    seconds.max(1) as u32
+}
+
+/// A pass-through command with its timeout. Every command gets a sensible
+/// per-opcode default so a hung drive can't block a diagnostic forever.
+pub struct ScsiCommand {
+    cdb: Vec<u8>,
+    timeout: Duration,
+}
+
+impl ScsiCommand {
+    pub fn new(cdb: &[u8]) -> Self {
+        ScsiCommand {
+            cdb: cdb.to_vec(),
+            timeout: cdb
+                .first()
+                .map_or(DEFAULT_TIMEOUT, |op| default_timeout(*op)),
+        }
+    }
+
+    /// Overrides the default timeout for this command.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs the command expecting data back from the device; returns the
+    /// number of bytes fetched into `buffer`.
+    pub fn get_data(
+        &self,
+        recorder: &IDiscRecorder2Ex,
+        buffer: &mut [u8],
+    ) -> Result<u32, BurnError> {
+        let mut sense = [0u8; SENSE_BUFFER_SIZE];
+        let mut fetched = 0u32;
+        unsafe {
+            recorder.SendCommandGetDataFromDevice(
+                self.cdb.as_ptr(),
+                self.cdb.len() as u32,
+                sense.as_mut_ptr(),
+                timeout_to_seconds(self.timeout),
+                buffer.as_mut_ptr(),
+                buffer.len() as u32,
+                &mut fetched,
+            )?;
+        }
+        Ok(fetched)
+    }
+
+    /// Runs a command that transfers no data.
+    pub fn no_data(&self, recorder: &IDiscRecorder2Ex) -> Result<(), BurnError> {
+        let mut sense = [0u8; SENSE_BUFFER_SIZE];
+        unsafe {
+            recorder.SendCommandNoData(
+                self.cdb.as_ptr(),
+                self.cdb.len() as u32,
+                sense.as_mut_ptr(),
+                timeout_to_seconds(self.timeout),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Upper bound on the data-in buffer handed to `send_command_in`, so a drive
+// reporting huge transfer limits doesn't cost a huge allocation per command.
+const MAX_COMMAND_BUFFER: usize = 64 * 1024;
+
+/// Runs `cdb` expecting data back from the device, sizing the buffer from
+/// the recorder's transfer limits and truncating it to the bytes actually
+/// fetched. Sense data from a failing command is folded into the error.
+pub fn send_command_in(
+    recorder: &IDiscRecorder2Ex,
+    cdb: &[u8],
+    timeout: Duration,
+) -> Result<Vec<u8>, BurnError> {
+    let limits = IoLimits::from_recorder(recorder)?;
+    let capacity = (limits.max_non_page_aligned_transfer as usize).min(MAX_COMMAND_BUFFER);
+    let mut buffer = vec![0u8; capacity.max(SECTOR_SIZE)];
+    let mut sense = [0u8; SENSE_BUFFER_SIZE];
+    let mut fetched = 0u32;
+    let sent = unsafe {
+        recorder.SendCommandGetDataFromDevice(
+            cdb.as_ptr(),
+            cdb.len() as u32,
+            sense.as_mut_ptr(),
+            timeout_to_seconds(timeout),
+            buffer.as_mut_ptr(),
+            buffer.len() as u32,
+            &mut fetched,
+        )
+    };
+    match sent {
+        Ok(()) => {
+            buffer.truncate(fetched as usize);
+            Ok(buffer)
+        }
+        Err(err) => Err(classify_burn_failure(err, Some(&sense))),
+    }
+}
+
+/// Runs `cdb` sending `data` to the device.
+pub fn send_command_out(
+    recorder: &IDiscRecorder2Ex,
+    cdb: &[u8],
+    data: &[u8],
+    timeout: Duration,
+) -> Result<(), BurnError> {
+    let mut sense = [0u8; SENSE_BUFFER_SIZE];
+    unsafe {
+        recorder.SendCommandSendDataToDevice(
+            cdb.as_ptr(),
+            cdb.len() as u32,
+            sense.as_mut_ptr(),
+            timeout_to_seconds(timeout),
+            data.as_ptr(),
+            data.len() as u32,
+        )
+    }
+    .map_err(|err| classify_burn_failure(err, Some(&sense)))
+}
+
+/// Runs a `cdb` that transfers no data.
+pub fn send_command_nodata(
+    recorder: &IDiscRecorder2Ex,
+    cdb: &[u8],
+    timeout: Duration,
+) -> Result<(), BurnError> {
+    let mut sense = [0u8; SENSE_BUFFER_SIZE];
+    unsafe {
+        recorder.SendCommandNoData(
+            cdb.as_ptr(),
+            cdb.len() as u32,
+            sense.as_mut_ptr(),
+            timeout_to_seconds(timeout),
+        )
+    }
+    .map_err(|err| classify_burn_failure(err, Some(&sense)))
+}
+
+/// Fetches the raw READ DISC INFORMATION response for the loaded media.
+pub(crate) fn get_disc_information_raw(recorder: &IDiscRecorder2Ex) -> Result<Vec<u8>, BurnError> {
+    let mut data = std::ptr::null_mut();
+    let mut size = 0u32;
+    unsafe {
+        recorder.GetDiscInformation(&mut data, &mut size)?;
+        if data.is_null() {
+            return Ok(Vec::new());
+        }
+        let bytes = std::slice::from_raw_parts(data, size as usize).to_vec();
+        CoTaskMemFree(Some(data as *const _));
+        Ok(bytes)
+    }
+}
+
+/// Fetches the raw READ TRACK INFORMATION response for `address`.
+pub(crate) fn get_track_information_raw(
+    recorder: &IDiscRecorder2Ex,
+    address: u32,
+    address_type: IMAPI_READ_TRACK_ADDRESS_TYPE,
+) -> Result<Vec<u8>, BurnError> {
+    let mut data = std::ptr::null_mut();
+    let mut size = 0u32;
+    unsafe {
+        recorder.GetTrackInformation(address, address_type, &mut data, &mut size)?;
+        if data.is_null() {
+            return Ok(Vec::new());
+        }
+        let bytes = std::slice::from_raw_parts(data, size as usize).to_vec();
+        CoTaskMemFree(Some(data as *const _));
+        Ok(bytes)
+    }
+}
+
+/// Fetches a raw mode page from the recorder. The returned bytes start at
+/// the page header (page code, length, ...).
+pub fn get_mode_page(
+    recorder: &IDiscRecorder2Ex,
+    page: IMAPI_MODE_PAGE_TYPE,
+    request: IMAPI_MODE_PAGE_REQUEST_TYPE,
+) -> Result<Vec<u8>, BurnError> {
+    let mut data = std::ptr::null_mut();
+    let mut size = 0u32;
+    unsafe {
+        recorder.GetModePage(page, request, &mut data, &mut size)?;
+        if data.is_null() {
+            return Ok(Vec::new());
+        }
+        let bytes = std::slice::from_raw_parts(data, size as usize).to_vec();
+        CoTaskMemFree(Some(data as *const _));
+        Ok(bytes)
+    }
+}
+
+/// Writes a raw mode page, as previously obtained from `get_mode_page`, back
+/// to the recorder.
+pub fn set_mode_page(
+    recorder: &IDiscRecorder2Ex,
+    request: IMAPI_MODE_PAGE_REQUEST_TYPE,
+    data: &[u8],
+) -> Result<(), BurnError> {
+    unsafe {
+        recorder.SetModePage(request, data.as_ptr(), data.len() as u32)?;
+    }
+    Ok(())
+}
+
+/// Transfer constraints reported by a recorder for pass-through commands.
+#[derive(Clone, Copy, Debug)]
+pub struct IoLimits {
+    /// Buffers must be aligned on `mask + 1` bytes.
+    pub byte_alignment_mask: u32,
+    /// Largest transfer allowed for page aligned buffers.
+    pub max_page_aligned_transfer: u32,
+    /// Largest transfer allowed for arbitrary buffers.
+    pub max_non_page_aligned_transfer: u32,
+}
+
+impl IoLimits {
+    /// Queries the transfer limits of `recorder`.
+    pub fn from_recorder(recorder: &IDiscRecorder2Ex) -> Result<IoLimits, BurnError> {
+        unsafe {
+            Ok(IoLimits {
+                byte_alignment_mask: recorder.GetByteAlignmentMask()?,
+                max_page_aligned_transfer: recorder.GetMaximumPageAlignedTransferSize()?,
+                max_non_page_aligned_transfer: recorder.GetMaximumNonPageAlignedTransferSize()?,
+            })
+        }
+    }
+
+    /// Largest whole number of sectors fitting in a single transfer.
+    pub(crate) fn sectors_per_transfer(&self) -> usize {
+        (self.max_non_page_aligned_transfer as usize / SECTOR_SIZE).max(1)
+    }
+
+    /// Clamps a wanted buffer length to something the drive accepts in one
+    /// transfer: no longer than the non-page-aligned maximum and rounded
+    /// down to the alignment unit, but never below one unit.
+    pub fn align_buffer_len(&self, len: usize) -> usize {
+        let unit = self.byte_alignment_mask as usize + 1;
+        let max = (self.max_non_page_aligned_transfer as usize / unit).max(1) * unit;
+        (len.min(max) / unit * unit).max(unit)
+    }
+}
+
+/// The transfer limits of `recorder`; shorthand for
+/// `IoLimits::from_recorder`.
+pub fn transfer_limits(recorder: &IDiscRecorder2Ex) -> Result<IoLimits, BurnError> {
+    IoLimits::from_recorder(recorder)
+}
+
+/// Issues a READ(10) for `sectors` sectors starting at `lba`, filling
+/// `buffer` which must be exactly `sectors * SECTOR_SIZE` bytes long.
+pub(crate) fn read_sectors(
+    recorder: &IDiscRecorder2Ex,
+    lba: u32,
+    sectors: u16,
+    buffer: &mut [u8],
+    timeout: Duration,
+) -> Result<(), BurnError> {
+    debug_assert_eq!(buffer.len(), sectors as usize * SECTOR_SIZE);
+    let cdb: [u8; 10] = [
+        0x28, // READ(10)
+        0,
+        (lba >> 24) as u8,
+        (lba >> 16) as u8,
+        (lba >> 8) as u8,
+        lba as u8,
+        0,
+        (sectors >> 8) as u8,
+        sectors as u8,
+        0,
+    ];
+    ScsiCommand::new(&cdb)
+        .timeout(timeout)
+        .get_data(recorder, buffer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn buffer_lengths_respect_limits() {
+        let limits = IoLimits {
+            byte_alignment_mask: 0x3,
+            max_page_aligned_transfer: 0x20000,
+            max_non_page_aligned_transfer: 0x10000,
+        };
+        // Already aligned and within limits: unchanged.
+        assert_eq!(limits.align_buffer_len(4096), 4096);
+        // Rounded down to the 4 byte unit.
+        assert_eq!(limits.align_buffer_len(4097), 4096);
+        // Clamped to the non-page-aligned maximum.
+        assert_eq!(limits.align_buffer_len(0x20000), 0x10000);
+        // Never shrinks below one alignment unit.
+        assert_eq!(limits.align_buffer_len(1), 4);
+    }
+
+    #[test]
+    fn timeout_conversion_rounds_up() {
+        assert_eq!(timeout_to_seconds(Duration::from_millis(1500)), 2);
+        assert_eq!(timeout_to_seconds(Duration::from_millis(1)), 1);
+        assert_eq!(timeout_to_seconds(Duration::from_secs(0)), 1);
+        assert_eq!(timeout_to_seconds(Duration::from_secs(30)), 30);
+        assert_eq!(
+            timeout_to_seconds(Duration::from_secs(u64::from(u32::MAX) + 10)),
+            u32::MAX
+        );
+    }
+
+    #[test]
+    fn per_opcode_defaults() {
+        assert_eq!(default_timeout(0x12), SHORT_TIMEOUT); // INQUIRY
+        assert_eq!(default_timeout(0xa1), LONG_TIMEOUT); // BLANK
+        assert_eq!(default_timeout(0x28), DEFAULT_TIMEOUT); // READ(10)
+    }
+}
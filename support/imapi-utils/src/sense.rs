@@ -0,0 +1,218 @@
+//! SCSI sense data parsing, used to classify burn failures that IMAPI only
+//! surfaces as generic write errors.
+
+use crate::error::BurnError;
+use crate::scsi::{ScsiCommand, SENSE_BUFFER_SIZE};
+use std::fmt;
+use windows::core::HRESULT;
+use windows::Win32::Storage::Imapi::IDiscRecorder2Ex;
+use windows::Win32::System::AddressBook::E_IMAPI_LOSS_OF_STREAMING;
+
+/// Decoded sense key / additional sense code pair from a drive sense buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SenseData {
+    pub sense_key: u8,
+    pub asc: u8,
+    pub ascq: u8,
+}
+
+impl SenseData {
+    /// Parses a raw sense buffer, accepting both the fixed (0x70/0x71) and
+    /// the descriptor (0x72/0x73) formats.
+    pub fn parse(buffer: &[u8]) -> Option<SenseData> {
+        match buffer.first()? & 0x7f {
+            0x70 | 0x71 => {
+                if buffer.len() < 14 {
+                    return None;
+                }
+                Some(SenseData {
+                    sense_key: buffer[2] & 0x0f,
+                    asc: buffer[12],
+                    ascq: buffer[13],
+                })
+            }
+            0x72 | 0x73 => {
+                if buffer.len() < 4 {
+                    return None;
+                }
+                Some(SenseData {
+                    sense_key: buffer[1] & 0x0f,
+                    asc: buffer[2],
+                    ascq: buffer[3],
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// True for the MMC power calibration area errors (ASC 0x73).
+    pub fn is_power_calibration_error(&self) -> bool {
+        self.asc == 0x73 && (0x01..=0x05).contains(&self.ascq)
+    }
+
+    /// True for the MMC "write error - loss of streaming" condition, i.e. a
+    /// buffer underrun.
+    pub fn is_buffer_underrun(&self) -> bool {
+        self.asc == 0x0c && self.ascq == 0x09
+    }
+}
+
+/// Broad classification of a hardware-level burn failure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BurnErrorKind {
+    PowerCalibration,
+    BufferUnderrun,
+    /// Neither the sense data nor the HRESULT matched a known class.
+    Other,
+}
+
+/// Everything known about a failed burn in one place: the IMAPI `HRESULT`,
+/// the drive sense data when it could be captured, and the classification
+/// derived from both. Meant for logging and user-facing messages.
+#[derive(Clone, Debug)]
+pub struct BurnFailure {
+    pub hresult: HRESULT,
+    pub sense: Option<SenseData>,
+    pub kind: BurnErrorKind,
+}
+
+impl BurnFailure {
+    /// Builds the failure from the write error, asking the drive for its
+    /// current sense data when a pass-through recorder is available. The
+    /// REQUEST SENSE itself failing is ignored: the HRESULT alone still
+    /// classifies loss-of-streaming.
+    pub fn capture(
+        error: windows::core::Error,
+        recorder: Option<&IDiscRecorder2Ex>,
+    ) -> BurnFailure {
+        let sense = recorder.and_then(request_sense);
+        let kind = match &sense {
+            Some(sense) if sense.is_power_calibration_error() => BurnErrorKind::PowerCalibration,
+            Some(sense) if sense.is_buffer_underrun() => BurnErrorKind::BufferUnderrun,
+            _ if error.code() == E_IMAPI_LOSS_OF_STREAMING => BurnErrorKind::BufferUnderrun,
+            _ => BurnErrorKind::Other,
+        };
+        BurnFailure {
+            hresult: error.code(),
+            sense,
+            kind,
+        }
+    }
+
+    /// Folds the failure into the crate error enum, preserving the sense
+    /// data on the classified variants.
+    pub fn into_error(self) -> BurnError {
+        match self.kind {
+            BurnErrorKind::PowerCalibration => BurnError::PowerCalibration(self.sense),
+            BurnErrorKind::BufferUnderrun => BurnError::BufferUnderrun(self.sense),
+            BurnErrorKind::Other => BurnError::Com(self.hresult.into()),
+        }
+    }
+}
+
+impl fmt::Display for BurnFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "burn failed with {:?} ({:#010x})",
+            self.kind, self.hresult.0
+        )?;
+        if let Some(sense) = &self.sense {
+            write!(
+                f,
+                ", sense key {:#04x} asc {:#04x} ascq {:#04x}",
+                sense.sense_key, sense.asc, sense.ascq
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Asks the drive for its current sense data. Any failure along the way
+// yields `None`; a failing REQUEST SENSE must never mask the original
+// error.
+fn request_sense(recorder: &IDiscRecorder2Ex) -> Option<SenseData> {
+    let cdb: [u8; 6] = [0x03, 0, 0, 0, SENSE_BUFFER_SIZE as u8, 0];
+    let mut buffer = [0u8; SENSE_BUFFER_SIZE];
+    let fetched = ScsiCommand::new(&cdb)
+        .get_data(recorder, &mut buffer)
+        .ok()?;
+    SenseData::parse(&buffer[..fetched as usize])
+}
+
+/// Classifies a failed burn into a specific `BurnError`, using the sense
+/// buffer captured from the recorder when the caller has one, and falling
+/// back to the IMAPI `HRESULT` otherwise.
+pub fn classify_burn_failure(
+    error: windows::core::Error,
+    sense_buffer: Option<&[u8]>,
+) -> BurnError {
+    let sense = sense_buffer.and_then(SenseData::parse);
+    if let Some(sense) = sense {
+        if sense.is_power_calibration_error() {
+            return BurnError::PowerCalibration(Some(sense));
+        }
+        if sense.is_buffer_underrun() {
+            return BurnError::BufferUnderrun(Some(sense));
+        }
+    }
+    if error.code() == E_IMAPI_LOSS_OF_STREAMING {
+        return BurnError::BufferUnderrun(sense);
+    }
+    BurnError::Com(error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fixed_format_power_calibration() {
+        // Fixed format, sense key 3 (MEDIUM ERROR), ASC 0x73 ASCQ 0x03.
+        let buffer = [
+            0x70, 0, 0x03, 0, 0, 0, 0, 0x0a, 0, 0, 0, 0, 0x73, 0x03, 0, 0, 0, 0,
+        ];
+        let sense = SenseData::parse(&buffer).unwrap();
+        assert_eq!(sense.sense_key, 0x03);
+        assert!(sense.is_power_calibration_error());
+        assert!(!sense.is_buffer_underrun());
+
+        match classify_burn_failure(
+            windows::core::Error::from(E_IMAPI_LOSS_OF_STREAMING),
+            Some(&buffer),
+        ) {
+            BurnError::PowerCalibration(Some(parsed)) => assert_eq!(parsed, sense),
+            other => panic!("unexpected classification: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn descriptor_format_buffer_underrun() {
+        // Descriptor format, sense key 3, ASC 0x0c ASCQ 0x09.
+        let buffer = [0x72, 0x03, 0x0c, 0x09, 0, 0, 0, 0];
+        let sense = SenseData::parse(&buffer).unwrap();
+        assert!(sense.is_buffer_underrun());
+
+        match classify_burn_failure(
+            windows::core::Error::from(E_IMAPI_LOSS_OF_STREAMING),
+            Some(&buffer),
+        ) {
+            BurnError::BufferUnderrun(Some(parsed)) => assert_eq!(parsed, sense),
+            other => panic!("unexpected classification: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn loss_of_streaming_without_sense() {
+        match classify_burn_failure(windows::core::Error::from(E_IMAPI_LOSS_OF_STREAMING), None) {
+            BurnError::BufferUnderrun(None) => {}
+            other => panic!("unexpected classification: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn truncated_buffer_is_rejected() {
+        assert_eq!(SenseData::parse(&[0x70, 0, 0x03]), None);
+        assert_eq!(SenseData::parse(&[]), None);
+    }
+}
@@ -0,0 +1,132 @@
+//! Write speed helpers for the data writer.
+
+use crate::error::BurnError;
+use crate::media::MediaType;
+use crate::safearray::{read_safearray_dispatch, read_safearray_i32};
+use windows::core::ComInterface;
+use windows::Win32::Foundation::VARIANT_BOOL;
+use windows::Win32::Storage::Imapi::{IDiscFormat2Data, IWriteSpeedDescriptor};
+
+/// Requested and negotiated write speed, queried as one snapshot so UIs can
+/// show "burning at 8x" once the drive picked the actual speed.
+#[derive(Clone, Copy, Debug)]
+pub struct WriteSpeedStatus {
+    /// Speed the client asked for, in sectors per second.
+    pub requested_sectors_per_sec: i32,
+    /// Speed the drive settled on, in sectors per second.
+    pub current_sectors_per_sec: i32,
+    /// Whether pure CAV rotation was requested.
+    pub requested_pure_cav: bool,
+    /// Whether the drive is actually using pure CAV rotation.
+    pub current_pure_cav: bool,
+}
+
+impl WriteSpeedStatus {
+    /// Negotiated speed in kilobytes per second (2048 byte data sectors).
+    pub fn kbps(&self) -> i32 {
+        self.current_sectors_per_sec * 2
+    }
+}
+
+/// Returns the write speeds (sectors per second) the drive supports for the
+/// current media, fastest first.
+pub fn supported_write_speeds(burner: &IDiscFormat2Data) -> Result<Vec<i32>, BurnError> {
+    let psa = unsafe { burner.SupportedWriteSpeeds()? };
+    let mut speeds = read_safearray_i32(psa)?;
+    speeds.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(speeds)
+}
+
+/// Reads the requested and current write speed properties of `burner` in one
+/// call, decoding the `VARIANT_BOOL` rotation flags.
+pub fn write_speed_status(burner: &IDiscFormat2Data) -> Result<WriteSpeedStatus, BurnError> {
+    unsafe {
+        Ok(WriteSpeedStatus {
+            requested_sectors_per_sec: burner.RequestedWriteSpeed()?,
+            current_sectors_per_sec: burner.CurrentWriteSpeed()?,
+            requested_pure_cav: burner.RequestedRotationTypeIsPureCAV()?.as_bool(),
+            current_pure_cav: burner.CurrentRotationTypeIsPureCAV()?.as_bool(),
+        })
+    }
+}
+
+/// One supported write configuration of the drive, decoded from an
+/// `IWriteSpeedDescriptor`.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WriteSpeedDescriptor {
+    pub media_type: MediaType,
+    /// Write speed in sectors per second.
+    pub write_speed: i32,
+    pub rotation_is_pure_cav: bool,
+}
+
+/// Decodes `SupportedWriteSpeedDescriptors` into plain Rust values, one per
+/// descriptor. An empty (or null) array yields an empty vector.
+pub fn write_speed_descriptors(
+    burner: &IDiscFormat2Data,
+) -> Result<Vec<WriteSpeedDescriptor>, BurnError> {
+    let psa = unsafe { burner.SupportedWriteSpeedDescriptors()? };
+    let mut descriptors = Vec::new();
+    for dispatch in read_safearray_dispatch(psa)? {
+        let descriptor: IWriteSpeedDescriptor = dispatch.cast()?;
+        unsafe {
+            descriptors.push(WriteSpeedDescriptor {
+                media_type: MediaType::from(descriptor.MediaType()?),
+                write_speed: descriptor.WriteSpeed()?,
+                rotation_is_pure_cav: descriptor.RotationTypeIsPureCAV()?.as_bool(),
+            });
+        }
+    }
+    Ok(descriptors)
+}
+
+// 1x transfer rates in sectors per second, per media family. The byte rates
+// are the classic 150 KiB/s (CD), 1,385,000 B/s (DVD), 4,495,625 B/s (BD)
+// and 4,568,750 B/s (HD DVD) divided by the 2048-byte sector.
+pub(crate) fn one_x_sectors_per_second(media: MediaType) -> Option<i32> {
+    match media {
+        MediaType::CdRom | MediaType::CdR | MediaType::CdRw => Some(75),
+        MediaType::DvdRom
+        | MediaType::DvdRam
+        | MediaType::DvdPlusR
+        | MediaType::DvdPlusRw
+        | MediaType::DvdPlusRDualLayer
+        | MediaType::DvdDashR
+        | MediaType::DvdDashRw
+        | MediaType::DvdDashRDualLayer
+        | MediaType::DvdPlusRwDualLayer => Some(676),
+        MediaType::BdRom | MediaType::BdR | MediaType::BdRe => Some(2195),
+        MediaType::HdDvdRom | MediaType::HdDvdR | MediaType::HdDvdRam => Some(2231),
+        MediaType::Unknown | MediaType::Disk => None,
+    }
+}
+
+// Requests `sectors_per_second` after checking it against the drive's
+// supported speeds; closest-match selection is left to the caller since
+// IMAPI itself already picks the nearest supported speed when asked.
+pub(crate) fn request_write_speed(
+    burner: &IDiscFormat2Data,
+    sectors_per_second: i32,
+) -> Result<(), BurnError> {
+    if !supported_write_speeds(burner)?.contains(&sectors_per_second) {
+        return Err(BurnError::Unsupported(
+            "the drive does not support the requested write speed",
+        ));
+    }
+    unsafe { burner.SetWriteSpeed(sectors_per_second, VARIANT_BOOL::from(false))? };
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn one_x_rates_per_family() {
+        assert_eq!(one_x_sectors_per_second(MediaType::CdR), Some(75));
+        assert_eq!(one_x_sectors_per_second(MediaType::DvdPlusRw), Some(676));
+        assert_eq!(one_x_sectors_per_second(MediaType::BdRe), Some(2195));
+        assert_eq!(one_x_sectors_per_second(MediaType::Unknown), None);
+    }
+}
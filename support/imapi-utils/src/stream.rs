@@ -0,0 +1,456 @@
+//! `IStream` helpers for feeding local data to the IMAPI writers.
+
+use crate::error::BurnError;
+use log::warn;
+use std::iter::once;
+use std::os::windows::ffi::OsStrExt;
+use std::os::windows::io::AsRawHandle;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use windows::core::{implement, HRESULT, PCWSTR};
+use windows::Win32::Foundation::{
+    CloseHandle, E_NOTIMPL, HANDLE, HGLOBAL, STG_E_ACCESSDENIED, STG_E_INVALIDFUNCTION,
+    STG_E_INVALIDPOINTER, STG_E_READFAULT, S_FALSE, S_OK,
+};
+use windows::Win32::Storage::Imapi::IFileSystemImageResult;
+use windows::Win32::System::Com::{
+    CreateStreamOnHGlobal, ISequentialStream_Impl, IStream, IStream_Impl, LOCKTYPE, STATFLAG,
+    STATSTG, STGC, STGM_READ, STGM_SHARE_DENY_WRITE, STGTY_STREAM, STREAM_SEEK, STREAM_SEEK_CUR,
+    STREAM_SEEK_END, STREAM_SEEK_SET,
+};
+use windows::Win32::System::Memory::{
+    CreateFileMappingW, MapViewOfFile, UnmapViewOfFile, FILE_MAP_READ, MEMORY_MAPPED_VIEW_ADDRESS,
+    PAGE_READONLY,
+};
+use windows::Win32::UI::Shell::SHCreateStreamOnFileEx;
+
+/// Opens a read-only `IStream` over a local file.
+pub(crate) fn file_stream(path: &Path) -> Result<IStream, BurnError> {
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(once(0)).collect();
+    unsafe {
+        Ok(SHCreateStreamOnFileEx(
+            PCWSTR(wide.as_ptr()),
+            (STGM_READ | STGM_SHARE_DENY_WRITE).0,
+            0,
+            false,
+            None,
+        )?)
+    }
+}
+
+/// Copies `bytes` into a freshly created in-memory `IStream`, rewound to the
+/// start so it can be handed to `IDiscFormat2Data::Write`.
+pub(crate) fn memory_stream(bytes: &[u8]) -> Result<IStream, BurnError> {
+    unsafe {
+        let stream = CreateStreamOnHGlobal(HGLOBAL::default(), true)?;
+        let mut written = 0u32;
+        stream
+            .Write(
+                bytes.as_ptr() as *const _,
+                bytes.len() as u32,
+                Some(&mut written),
+            )
+            .ok()?;
+        stream.Seek(0, STREAM_SEEK_SET, None)?;
+        Ok(stream)
+    }
+}
+
+/// `std::io::Write` sink accumulating into a growable COM stream, so content
+/// generated programmatically (e.g. an ISO produced by another crate) can be
+/// burned without staging a file tree first.
+pub struct StreamSink {
+    stream: IStream,
+}
+
+impl StreamSink {
+    pub fn new() -> Result<StreamSink, BurnError> {
+        // The HGLOBAL backing is created empty and regrows on demand, so
+        // writes beyond the initial allocation are fine.
+        let stream = unsafe { CreateStreamOnHGlobal(HGLOBAL::default(), true)? };
+        Ok(StreamSink { stream })
+    }
+
+    /// Rewinds the accumulated content and hands it out, ready for
+    /// `IDiscFormat2Data::Write`.
+    pub fn into_stream(self) -> Result<IStream, BurnError> {
+        unsafe { self.stream.Seek(0, STREAM_SEEK_SET, None)? };
+        Ok(self.stream)
+    }
+}
+
+impl std::io::Write for StreamSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // A single COM write is capped at u32::MAX bytes; report the short
+        // write and let the caller loop.
+        let chunk = buf.len().min(u32::MAX as usize) as u32;
+        let mut written = 0u32;
+        unsafe {
+            self.stream
+                .Write(buf.as_ptr() as *const _, chunk, Some(&mut written))
+                .ok()
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+        }
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// Chunk size used when draining an `IStream` into local memory or a file.
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Read side of a result image, typically obtained from
+/// `IFileSystemImageResult::ImageStream`, with conversions suited to the
+/// post-burn verify path.
+pub struct ResultImageStream {
+    stream: IStream,
+}
+
+impl ResultImageStream {
+    pub fn new(stream: IStream) -> ResultImageStream {
+        ResultImageStream { stream }
+    }
+
+    /// Wraps the image stream of a freshly created result image.
+    pub fn from_result(result: &IFileSystemImageResult) -> Result<ResultImageStream, BurnError> {
+        Ok(ResultImageStream::new(unsafe { result.ImageStream()? }))
+    }
+
+    // Rewinds the stream and copies it whole into `sink`.
+    fn drain_to(&self, sink: &mut impl std::io::Write) -> Result<(), BurnError> {
+        unsafe { self.stream.Seek(0, STREAM_SEEK_SET, None)? };
+        let mut chunk = vec![0u8; COPY_CHUNK_SIZE];
+        loop {
+            let mut read = 0u32;
+            // S_FALSE signals a short read at the end, so only the error
+            // bit matters here.
+            let hr = unsafe {
+                self.stream.Read(
+                    chunk.as_mut_ptr() as *mut _,
+                    chunk.len() as u32,
+                    Some(&mut read),
+                )
+            };
+            if hr.is_err() {
+                return Err(windows::core::Error::from(hr).into());
+            }
+            if read == 0 {
+                return Ok(());
+            }
+            sink.write_all(&chunk[..read as usize])?;
+        }
+    }
+
+    /// Copies the whole image into a `Vec`, giving the verify path a plain
+    /// slice to compare against disc reads.
+    ///
+    /// This holds the entire image in RAM; fine for CD sized images, but for
+    /// DVD or Blu-ray sized ones prefer `to_mmap` which lets the OS page the
+    /// data in and out on demand.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BurnError> {
+        let mut bytes = Vec::new();
+        self.drain_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Stages the image to a temporary file and memory-maps it read-only.
+    ///
+    /// The staging costs one full copy of the image to disk, but the compare
+    /// afterwards only keeps the pages currently being looked at resident,
+    /// so the working set stays small regardless of image size. The temp
+    /// file is deleted when the returned mapping is dropped.
+    pub fn to_mmap(&self) -> Result<MappedImage, BurnError> {
+        static STAGING_SERIAL: AtomicU64 = AtomicU64::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "imapi-result-{}-{}.img",
+            std::process::id(),
+            STAGING_SERIAL.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let staged = (|| -> Result<MappedImage, BurnError> {
+            let mut file = std::fs::File::create(&path)?;
+            self.drain_to(&mut file)?;
+            file.sync_all()?;
+            let len = file.metadata()?.len() as usize;
+            MappedImage::map(file, path.clone(), len)
+        })();
+        if staged.is_err() {
+            // Best effort: the file is useless without the mapping.
+            let _ = std::fs::remove_file(&path);
+        }
+        staged
+    }
+}
+
+/// Read-only memory mapping over a staged image, dereferencing to the byte
+/// slice. Unmaps and removes the backing temp file on drop.
+pub struct MappedImage {
+    view: MEMORY_MAPPED_VIEW_ADDRESS,
+    len: usize,
+    // Kept alive for the duration of the mapping.
+    _file: std::fs::File,
+    path: std::path::PathBuf,
+}
+
+impl MappedImage {
+    fn map(
+        file: std::fs::File,
+        path: std::path::PathBuf,
+        len: usize,
+    ) -> Result<MappedImage, BurnError> {
+        unsafe {
+            // An empty file can't be mapped; an empty slice is the honest
+            // answer for an empty image.
+            if len == 0 {
+                return Ok(MappedImage {
+                    view: MEMORY_MAPPED_VIEW_ADDRESS::default(),
+                    len,
+                    _file: file,
+                    path,
+                });
+            }
+            let mapping = CreateFileMappingW(
+                HANDLE(file.as_raw_handle() as isize),
+                None,
+                PAGE_READONLY,
+                0,
+                0,
+                None,
+            )?;
+            let view = MapViewOfFile(mapping, FILE_MAP_READ, 0, 0, 0);
+            // The mapping handle can be closed right away; the view keeps
+            // the mapping alive.
+            let _ = CloseHandle(mapping);
+            if view.Value.is_null() {
+                return Err(windows::core::Error::from_win32().into());
+            }
+            Ok(MappedImage {
+                view,
+                len,
+                _file: file,
+                path,
+            })
+        }
+    }
+}
+
+impl std::ops::Deref for MappedImage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        if self.len == 0 {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.view.Value as *const u8, self.len) }
+        }
+    }
+}
+
+impl Drop for MappedImage {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.view.Value.is_null() {
+                let _ = UnmapViewOfFile(self.view);
+            }
+        }
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            warn!(
+                "Failed to remove staged image {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+/// A seekable source that knows its total length up front.
+///
+/// IMAPI uses `IStream::Stat` to estimate progress and ETA during a burn;
+/// implementing this instead of plain `Read + Seek` makes that estimate
+/// exact.
+pub trait SizedRead: std::io::Read + std::io::Seek {
+    /// Total length of the source in bytes.
+    fn len(&self) -> u64;
+
+    /// Whether the source holds no bytes at all.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: AsRef<[u8]>> SizedRead for std::io::Cursor<T> {
+    fn len(&self) -> u64 {
+        self.get_ref().as_ref().len() as u64
+    }
+}
+
+impl SizedRead for std::fs::File {
+    fn len(&self) -> u64 {
+        // A file whose length can't be read will fail the burn anyway; zero
+        // just degrades the ETA.
+        self.metadata().map(|meta| meta.len()).unwrap_or(0)
+    }
+}
+
+/// Read-only `IStream` adapter over a Rust `SizedRead` source, reporting the
+/// exact size through `Stat` so IMAPI's progress estimation has a correct
+/// total to work with.
+#[implement(IStream)]
+pub struct ReadSeekStream<T: SizedRead + 'static> {
+    source: Mutex<T>,
+}
+
+impl<T: SizedRead + 'static> ReadSeekStream<T> {
+    pub fn new(source: T) -> ReadSeekStream<T> {
+        ReadSeekStream {
+            source: Mutex::new(source),
+        }
+    }
+
+    /// Wraps the source into the COM interface IMAPI consumes.
+    pub fn into_stream(self) -> IStream {
+        self.into()
+    }
+}
+
+impl ReadSeekStream<std::fs::File> {
+    /// Adapts the file at `path` directly, without buffering it in memory;
+    /// `Stat` reports the file size from its metadata.
+    pub fn from_path(path: &Path) -> Result<IStream, BurnError> {
+        Ok(ReadSeekStream::new(std::fs::File::open(path)?).into_stream())
+    }
+}
+
+impl<T: SizedRead + 'static> ISequentialStream_Impl for ReadSeekStream<T> {
+    fn Read(&self, pv: *mut std::ffi::c_void, cb: u32, pcbread: *mut u32) -> HRESULT {
+        let mut source = self.source.lock().unwrap();
+        let buffer = unsafe { std::slice::from_raw_parts_mut(pv as *mut u8, cb as usize) };
+        let mut filled = 0usize;
+        // Loop so a short read from the source isn't mistaken for the end of
+        // the stream.
+        while filled < buffer.len() {
+            match source.read(&mut buffer[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(ref err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(_) => return STG_E_READFAULT,
+            }
+        }
+        if !pcbread.is_null() {
+            unsafe { *pcbread = filled as u32 };
+        }
+        if filled == buffer.len() {
+            S_OK
+        } else {
+            S_FALSE
+        }
+    }
+
+    fn Write(&self, _pv: *const std::ffi::c_void, _cb: u32, _pcbwritten: *mut u32) -> HRESULT {
+        STG_E_ACCESSDENIED
+    }
+}
+
+impl<T: SizedRead + 'static> IStream_Impl for ReadSeekStream<T> {
+    fn Seek(
+        &self,
+        dlibmove: i64,
+        dworigin: STREAM_SEEK,
+        plibnewposition: *mut u64,
+    ) -> windows::core::Result<()> {
+        let from = match dworigin {
+            STREAM_SEEK_SET => std::io::SeekFrom::Start(dlibmove as u64),
+            STREAM_SEEK_CUR => std::io::SeekFrom::Current(dlibmove),
+            STREAM_SEEK_END => std::io::SeekFrom::End(dlibmove),
+            _ => return Err(STG_E_INVALIDFUNCTION.into()),
+        };
+        let position = self
+            .source
+            .lock()
+            .unwrap()
+            .seek(from)
+            .map_err(|_| windows::core::Error::from(STG_E_INVALIDPOINTER))?;
+        if !plibnewposition.is_null() {
+            unsafe { *plibnewposition = position };
+        }
+        Ok(())
+    }
+
+    fn SetSize(&self, _libnewsize: u64) -> windows::core::Result<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn CopyTo(
+        &self,
+        _pstm: Option<&IStream>,
+        _cb: u64,
+        _pcbread: *mut u64,
+        _pcbwritten: *mut u64,
+    ) -> windows::core::Result<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn Commit(&self, _grfcommitflags: &STGC) -> windows::core::Result<()> {
+        Ok(())
+    }
+
+    fn Revert(&self) -> windows::core::Result<()> {
+        Ok(())
+    }
+
+    fn LockRegion(
+        &self,
+        _liboffset: u64,
+        _cb: u64,
+        _dwlocktype: &LOCKTYPE,
+    ) -> windows::core::Result<()> {
+        Err(STG_E_INVALIDFUNCTION.into())
+    }
+
+    fn UnlockRegion(
+        &self,
+        _liboffset: u64,
+        _cb: u64,
+        _dwlocktype: u32,
+    ) -> windows::core::Result<()> {
+        Err(STG_E_INVALIDFUNCTION.into())
+    }
+
+    fn Stat(&self, pstatstg: *mut STATSTG, _grfstatflag: &STATFLAG) -> windows::core::Result<()> {
+        if pstatstg.is_null() {
+            return Err(STG_E_INVALIDPOINTER.into());
+        }
+        unsafe {
+            let stat = &mut *pstatstg;
+            *stat = STATSTG::default();
+            stat.r#type = STGTY_STREAM.0 as u32;
+            // The exact size is what tightens the IMAPI write ETA.
+            stat.cbSize = self.source.lock().unwrap().len();
+            stat.grfMode = STGM_READ.0;
+        }
+        Ok(())
+    }
+
+    fn Clone(&self) -> windows::core::Result<IStream> {
+        Err(E_NOTIMPL.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use windows::Win32::System::Com::STATFLAG_NONAME;
+
+    #[test]
+    fn stat_reports_exact_size() {
+        let bytes = vec![0u8; 12345];
+        let stream = ReadSeekStream::new(std::io::Cursor::new(bytes)).into_stream();
+        let mut stat = STATSTG::default();
+        unsafe { stream.Stat(&mut stat, STATFLAG_NONAME).unwrap() };
+        assert_eq!(stat.cbSize, 12345);
+    }
+}
@@ -0,0 +1,231 @@
+//! READ TOC pass-through support for audio discs.
+
+use crate::error::BurnError;
+use crate::safearray::read_safearray_i32;
+use crate::scsi::ScsiCommand;
+use windows::Win32::Storage::Imapi::{IDiscFormat2TrackAtOnce, IDiscRecorder2Ex};
+
+// Header plus up to 99 tracks and the lead-out, 8 bytes each.
+const READ_TOC_BUFFER_SIZE: usize = 4 + 100 * 8;
+// Track number of the lead-out descriptor.
+const LEAD_OUT_TRACK: u8 = 0xaa;
+
+/// A minute/second/frame position on a CD.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Msf {
+    pub minute: u8,
+    pub second: u8,
+    pub frame: u8,
+}
+
+impl Msf {
+    /// Logical block address of this position (75 frames per second, minus
+    /// the 2 second lead-in offset).
+    pub fn to_lba(self) -> i32 {
+        (i32::from(self.minute) * 60 + i32::from(self.second)) * 75 + i32::from(self.frame) - 150
+    }
+}
+
+/// One TOC entry.
+#[derive(Clone, Copy, Debug)]
+pub struct AudioTocTrack {
+    pub number: u8,
+    pub start: Msf,
+    /// False for data tracks on mixed-mode discs.
+    pub is_audio: bool,
+}
+
+/// Parsed table of contents of an audio (or mixed-mode) disc.
+#[derive(Clone, Debug)]
+pub struct AudioToc {
+    pub first_track: u8,
+    pub last_track: u8,
+    pub tracks: Vec<AudioTocTrack>,
+    /// Start of the lead-out, i.e. the end of the last track.
+    pub lead_out: Msf,
+}
+
+impl AudioToc {
+    pub fn track_count(&self) -> usize {
+        self.tracks.len()
+    }
+}
+
+// Parses a formatted READ TOC response requested in MSF form.
+fn parse_toc(response: &[u8]) -> Result<AudioToc, BurnError> {
+    if response.len() < 4 {
+        return Err(BurnError::MalformedResponse("READ TOC header too short"));
+    }
+    let data_length = usize::from(u16::from_be_bytes([response[0], response[1]]));
+    let end = (data_length + 2).min(response.len());
+    let first_track = response[2];
+    let last_track = response[3];
+
+    let mut tracks = Vec::new();
+    let mut lead_out = None;
+    for descriptor in response[4..end].chunks_exact(8) {
+        let control = descriptor[1] & 0x0f;
+        let number = descriptor[2];
+        let start = Msf {
+            minute: descriptor[5],
+            second: descriptor[6],
+            frame: descriptor[7],
+        };
+        if number == LEAD_OUT_TRACK {
+            lead_out = Some(start);
+        } else {
+            tracks.push(AudioTocTrack {
+                number,
+                start,
+                // Control bit 2 set means a data track.
+                is_audio: control & 0x04 == 0,
+            });
+        }
+    }
+
+    match lead_out {
+        Some(lead_out) => Ok(AudioToc {
+            first_track,
+            last_track,
+            tracks,
+            lead_out,
+        }),
+        None => Err(BurnError::MalformedResponse("READ TOC lacks a lead-out")),
+    }
+}
+
+/// Reads and parses the full table of contents of the disc in `recorder`.
+pub fn read_audio_toc(recorder: &IDiscRecorder2Ex) -> Result<AudioToc, BurnError> {
+    let cdb: [u8; 10] = [
+        0x43, // READ TOC/PMA/ATIP
+        0x02, // MSF addressing
+        0,    // format 0: formatted TOC
+        0,
+        0,
+        0,
+        0, // starting track
+        (READ_TOC_BUFFER_SIZE >> 8) as u8,
+        READ_TOC_BUFFER_SIZE as u8,
+        0,
+    ];
+    let mut buffer = [0u8; READ_TOC_BUFFER_SIZE];
+    let fetched = ScsiCommand::new(&cdb).get_data(recorder, &mut buffer)?;
+    parse_toc(&buffer[..fetched as usize])
+}
+
+/// One entry of a writer's expected table of contents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TocEntry {
+    /// Track number, or `0xaa` for the lead-out entry.
+    pub number: u8,
+    pub start_lba: i32,
+}
+
+impl TocEntry {
+    /// Whether this entry is the lead-out rather than a real track.
+    pub fn is_lead_out(&self) -> bool {
+        self.number == LEAD_OUT_TRACK
+    }
+}
+
+// Maps the positional LBA list of `ExpectedTableOfContents` to numbered
+// entries: index `n` is track `n + 1`, and the final value is the start of
+// the lead-out.
+fn entries_from_lbas(lbas: &[i32]) -> Vec<TocEntry> {
+    match lbas.split_last() {
+        None => Vec::new(),
+        Some((lead_out, tracks)) => tracks
+            .iter()
+            .enumerate()
+            .map(|(index, lba)| TocEntry {
+                number: (index + 1) as u8,
+                start_lba: *lba,
+            })
+            .chain(std::iter::once(TocEntry {
+                number: LEAD_OUT_TRACK,
+                start_lba: *lead_out,
+            }))
+            .collect(),
+    }
+}
+
+/// The table of contents the track-at-once writer expects to produce,
+/// letting authoring tools preview the layout before burning.
+pub fn expected_toc(writer: &IDiscFormat2TrackAtOnce) -> Result<Vec<TocEntry>, BurnError> {
+    let psa = unsafe { writer.ExpectedTableOfContents()? };
+    Ok(entries_from_lbas(&read_safearray_i32(psa)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_two_track_disc() {
+        // Header: length 26, tracks 1..=2, then two audio tracks and the
+        // lead-out.
+        let response = [
+            0x00, 0x1a, 0x01, 0x02, // header
+            0x00, 0x10, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, // track 1 at 00:02:00
+            0x00, 0x10, 0x02, 0x00, 0x00, 0x03, 0x1d, 0x2f, // track 2 at 03:29:47
+            0x00, 0x10, 0xaa, 0x00, 0x00, 0x07, 0x10, 0x00, // lead-out at 07:16:00
+        ];
+        let toc = parse_toc(&response).unwrap();
+        assert_eq!(toc.first_track, 1);
+        assert_eq!(toc.last_track, 2);
+        assert_eq!(toc.track_count(), 2);
+        assert!(toc.tracks.iter().all(|track| track.is_audio));
+        assert_eq!(toc.tracks[0].start.to_lba(), 0);
+        assert_eq!(
+            toc.lead_out,
+            Msf {
+                minute: 7,
+                second: 16,
+                frame: 0
+            }
+        );
+    }
+
+    #[test]
+    fn data_track_is_flagged() {
+        let response = [
+            0x00, 0x12, 0x01, 0x01, // header
+            0x00, 0x14, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, // data track
+            0x00, 0x10, 0xaa, 0x00, 0x00, 0x20, 0x00, 0x00, // lead-out
+        ];
+        let toc = parse_toc(&response).unwrap();
+        assert!(!toc.tracks[0].is_audio);
+    }
+
+    #[test]
+    fn expected_toc_numbering() {
+        let entries = entries_from_lbas(&[0, 15_000, 33_000]);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[0],
+            TocEntry {
+                number: 1,
+                start_lba: 0
+            }
+        );
+        assert_eq!(
+            entries[1],
+            TocEntry {
+                number: 2,
+                start_lba: 15_000
+            }
+        );
+        assert!(entries[2].is_lead_out());
+        assert_eq!(entries[2].start_lba, 33_000);
+        assert!(entries_from_lbas(&[]).is_empty());
+    }
+
+    #[test]
+    fn missing_lead_out_is_rejected() {
+        let response = [
+            0x00, 0x0a, 0x01, 0x01, // header
+            0x00, 0x10, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00,
+        ];
+        assert!(parse_toc(&response).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! Structured view of the MMC READ TRACK INFORMATION block.
+
+use crate::error::BurnError;
+use crate::scsi::get_track_information_raw;
+use windows::Win32::Storage::Imapi::{IDiscRecorder2Ex, IMAPI_READ_TRACK_ADDRESS_TYPE};
+
+// The fixed part of the block, through the track size field.
+const FIXED_HEADER_LENGTH: usize = 28;
+
+/// Parsed READ TRACK INFORMATION block for a single track.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackInfo {
+    pub track_number: u16,
+    pub session_number: u16,
+    /// First LBA of the track.
+    pub track_start_address: u32,
+    /// Track length in sectors.
+    pub track_size: u32,
+    /// Sectors still writable in the track.
+    pub free_blocks: u32,
+    /// Whether the track is blank.
+    pub blank: bool,
+}
+
+/// Parses a raw MMC TRACK INFORMATION blob.
+pub fn parse_track_information(data: &[u8]) -> Result<TrackInfo, BurnError> {
+    if data.len() < FIXED_HEADER_LENGTH {
+        return Err(BurnError::MalformedResponse("track information too short"));
+    }
+    // Track and session numbers are split into LSB (bytes 2 and 3) and MSB
+    // (bytes 32 and 33) halves; the MSB halves only exist in long blocks.
+    let (track_msb, session_msb) = if data.len() >= 34 {
+        (data[32], data[33])
+    } else {
+        (0, 0)
+    };
+    Ok(TrackInfo {
+        track_number: u16::from_be_bytes([track_msb, data[2]]),
+        session_number: u16::from_be_bytes([session_msb, data[3]]),
+        track_start_address: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
+        track_size: u32::from_be_bytes([data[24], data[25], data[26], data[27]]),
+        free_blocks: u32::from_be_bytes([data[16], data[17], data[18], data[19]]),
+        blank: data[6] & 0x40 != 0,
+    })
+}
+
+/// Reads and parses the track information for `address`, interpreted per
+/// `address_type` (LBA, track number or session number).
+pub fn track_information(
+    recorder: &IDiscRecorder2Ex,
+    address: u32,
+    address_type: IMAPI_READ_TRACK_ADDRESS_TYPE,
+) -> Result<TrackInfo, BurnError> {
+    parse_track_information(&get_track_information_raw(recorder, address, address_type)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_blank_track() {
+        let mut data = vec![0u8; 34];
+        data[2] = 0x02; // track LSB
+        data[3] = 0x01; // session LSB
+        data[6] = 0x40; // blank
+        data[8..12].copy_from_slice(&0x0001_2c00u32.to_be_bytes());
+        data[16..20].copy_from_slice(&1000u32.to_be_bytes());
+        data[24..28].copy_from_slice(&1000u32.to_be_bytes());
+        let info = parse_track_information(&data).unwrap();
+        assert_eq!(info.track_number, 2);
+        assert_eq!(info.session_number, 1);
+        assert_eq!(info.track_start_address, 0x0001_2c00);
+        assert_eq!(info.track_size, 1000);
+        assert_eq!(info.free_blocks, 1000);
+        assert!(info.blank);
+    }
+
+    #[test]
+    fn msb_halves_extend_the_numbers() {
+        let mut data = vec![0u8; 34];
+        data[2] = 0x01;
+        data[3] = 0x01;
+        data[32] = 0x01; // track MSB
+        let info = parse_track_information(&data).unwrap();
+        assert_eq!(info.track_number, 0x0101);
+        assert_eq!(info.session_number, 1);
+        assert!(!info.blank);
+    }
+
+    #[test]
+    fn short_block_is_rejected() {
+        assert!(parse_track_information(&[0u8; 12]).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+//! `BSTR` conversion helpers shared across the wrappers.
+
+use windows::core::BSTR;
+
+/// Decodes `value` into a `String`. Unlike going through `Display`, this
+/// preserves embedded NULs (a `BSTR` carries an explicit length) and maps
+/// unpaired surrogates to the replacement character instead of failing.
+pub fn bstr_to_string(value: &BSTR) -> String {
+    String::from_utf16_lossy(value.as_wide())
+}
+
+/// Encodes `value` as a `BSTR`. An empty string becomes the empty `BSTR`
+/// (a null pointer), which IMAPI accepts wherever a string is optional.
+pub fn string_to_bstr(value: &str) -> BSTR {
+    BSTR::from(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_non_ascii() {
+        for name in ["Sauvegarde n°3", "写真 2024", "naïve"] {
+            assert_eq!(bstr_to_string(&string_to_bstr(name)), name);
+        }
+    }
+
+    #[test]
+    fn preserves_embedded_nul() {
+        let bstr = BSTR::from_wide(&[0x61, 0x00, 0x62]).unwrap();
+        assert_eq!(bstr_to_string(&bstr), "a\0b");
+    }
+
+    #[test]
+    fn empty_string_round_trips() {
+        let bstr = string_to_bstr("");
+        assert!(bstr.is_empty());
+        assert_eq!(bstr_to_string(&bstr), "");
+    }
+}
@@ -0,0 +1,166 @@
+//! Read-back verification of a burned disc against the source image.
+
+use crate::error::BurnError;
+use crate::scsi::{self, IoLimits, SECTOR_SIZE};
+use std::io::Read;
+use std::time::Duration;
+use windows::core::ComInterface;
+use windows::Win32::Storage::Imapi::{
+    IBurnVerification, IDiscFormat2Data, IDiscRecorder2Ex, IMAPI_BURN_VERIFICATION_FULL,
+    IMAPI_BURN_VERIFICATION_LEVEL, IMAPI_BURN_VERIFICATION_NONE, IMAPI_BURN_VERIFICATION_QUICK,
+};
+
+// Generous per-command timeout: drives can stall on the first reads after a
+// burn while they refocus.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Friendly spelling of `IMAPI_BURN_VERIFICATION_LEVEL`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationLevel {
+    /// No verification after the burn.
+    None,
+    /// Quick sampled verification.
+    Quick,
+    /// Full read-back verification.
+    Full,
+}
+
+impl From<VerificationLevel> for IMAPI_BURN_VERIFICATION_LEVEL {
+    fn from(value: VerificationLevel) -> Self {
+        match value {
+            VerificationLevel::None => IMAPI_BURN_VERIFICATION_NONE,
+            VerificationLevel::Quick => IMAPI_BURN_VERIFICATION_QUICK,
+            VerificationLevel::Full => IMAPI_BURN_VERIFICATION_FULL,
+        }
+    }
+}
+
+impl From<IMAPI_BURN_VERIFICATION_LEVEL> for VerificationLevel {
+    fn from(value: IMAPI_BURN_VERIFICATION_LEVEL) -> Self {
+        match value {
+            IMAPI_BURN_VERIFICATION_QUICK => VerificationLevel::Quick,
+            IMAPI_BURN_VERIFICATION_FULL => VerificationLevel::Full,
+            _ => VerificationLevel::None,
+        }
+    }
+}
+
+/// Sets the drive-side verification level of `burner`, querying its
+/// `IBurnVerification` interface.
+pub fn set_verification(
+    burner: &IDiscFormat2Data,
+    level: VerificationLevel,
+) -> Result<(), BurnError> {
+    let verification: IBurnVerification = burner.cast()?;
+    unsafe { verification.SetBurnVerificationLevel(level.into())? };
+    Ok(())
+}
+
+/// The currently configured verification level of `burner`.
+pub fn verification(burner: &IDiscFormat2Data) -> Result<VerificationLevel, BurnError> {
+    let verification: IBurnVerification = burner.cast()?;
+    Ok(unsafe { verification.BurnVerificationLevel()? }.into())
+}
+
+/// Outcome of a verification pass.
+#[derive(Clone, Copy, Debug)]
+pub struct VerifyOutcome {
+    /// Whether the disc matched the source stream completely.
+    pub matches: bool,
+    /// First mismatching LBA, when `matches` is false because of a
+    /// comparison failure.
+    pub mismatch_lba: Option<u32>,
+    /// Number of sectors that were compared.
+    pub sectors_compared: u64,
+}
+
+/// Compares the first `total_sectors` sectors of the disc against
+/// `expected`, chunking reads per the drive's transfer limits.
+///
+/// A content mismatch fails with `BurnError::VerificationFailed` carrying
+/// the first differing LBA; `Ok(true)` means everything compared equal.
+pub fn verify_written(
+    recorder: &IDiscRecorder2Ex,
+    expected: impl Read,
+    total_sectors: i32,
+) -> Result<bool, BurnError> {
+    let byte_limit = u64::from(total_sectors.max(0) as u32) * SECTOR_SIZE as u64;
+    let outcome = verify_disc(recorder, expected.take(byte_limit), |_| {})?;
+    match outcome.mismatch_lba {
+        Some(lba) => Err(BurnError::VerificationFailed(lba)),
+        None => Ok(outcome.matches),
+    }
+}
+
+/// Reads the disc in `recorder` back through pass-through READ commands and
+/// compares it against `expected`, calling `progress` with the cumulative
+/// number of bytes verified. Stops at the first mismatch.
+pub fn verify_disc<R: Read, P: FnMut(u64)>(
+    recorder: &IDiscRecorder2Ex,
+    mut expected: R,
+    mut progress: P,
+) -> Result<VerifyOutcome, BurnError> {
+    let limits = IoLimits::from_recorder(recorder)?;
+    // Keep the sector count per command within a READ(10) transfer length.
+    let chunk_sectors = limits.sectors_per_transfer().min(u16::MAX as usize);
+
+    let mut disc_buffer = vec![0u8; chunk_sectors * SECTOR_SIZE];
+    let mut source_buffer = vec![0u8; chunk_sectors * SECTOR_SIZE];
+
+    let mut lba = 0u32;
+    let mut sectors_compared = 0u64;
+    let mut bytes_verified = 0u64;
+
+    loop {
+        // Fill as much of the source buffer as the stream still has.
+        let mut filled = 0;
+        while filled < source_buffer.len() {
+            match expected.read(&mut source_buffer[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        if filled == 0 {
+            // Source exhausted: everything matched.
+            return Ok(VerifyOutcome {
+                matches: true,
+                mismatch_lba: None,
+                sectors_compared,
+            });
+        }
+
+        let sectors = ((filled + SECTOR_SIZE - 1) / SECTOR_SIZE) as u16;
+        let disc_chunk = &mut disc_buffer[..sectors as usize * SECTOR_SIZE];
+        scsi::read_sectors(recorder, lba, sectors, disc_chunk, READ_TIMEOUT)?;
+
+        // Only compare the bytes the source actually provided; the tail of
+        // the last sector is drive padding.
+        if disc_chunk[..filled] != source_buffer[..filled] {
+            let offset = disc_chunk[..filled]
+                .iter()
+                .zip(&source_buffer[..filled])
+                .position(|(a, b)| a != b)
+                .unwrap_or(0);
+            return Ok(VerifyOutcome {
+                matches: false,
+                mismatch_lba: Some(lba + (offset / SECTOR_SIZE) as u32),
+                sectors_compared: sectors_compared + (offset / SECTOR_SIZE) as u64,
+            });
+        }
+
+        lba += u32::from(sectors);
+        sectors_compared += u64::from(sectors);
+        bytes_verified += filled as u64;
+        progress(bytes_verified);
+
+        if filled < source_buffer.len() {
+            // Short read means the stream hit EOF within this chunk.
+            return Ok(VerifyOutcome {
+                matches: true,
+                mismatch_lba: None,
+                sectors_compared,
+            });
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Device arrival/removal notifications from `IDiscMaster2`.
+
+use crate::com::ComApartment;
+use crate::error::BurnError;
+use crate::events::{
+    variant_to_bstr, EventCookie, DISPID_DDISCMASTER2EVENTS_DEVICE_ADDED,
+    DISPID_DDISCMASTER2EVENTS_DEVICE_REMOVED,
+};
+use crate::factory::new_disc_master2;
+use crate::util::bstr_to_string;
+use std::sync::mpsc;
+use std::task::{Context, Poll};
+use windows::core::{
+    implement, ComInterface, Error, IUnknown, Result as ComResult, BSTR, GUID, PCWSTR,
+};
+use windows::Win32::Foundation::{
+    DISP_E_BADPARAMCOUNT, DISP_E_MEMBERNOTFOUND, E_NOTIMPL, E_POINTER,
+};
+use windows::Win32::Storage::Imapi::{DDiscMaster2Events, DDiscMaster2Events_Impl, IDiscMaster2};
+use windows::Win32::System::Com::{
+    IDispatch, IDispatch_Impl, ITypeInfo, DISPATCH_FLAGS, DISPPARAMS, EXCEPINFO,
+};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::System::Variant::VARIANT;
+use windows::Win32::UI::WindowsAndMessaging::{
+    DispatchMessageW, GetMessageW, PostThreadMessageW, MSG, WM_QUIT,
+};
+
+/// An optical drive appearing on or disappearing from the system. The
+/// payload is the IMAPI unique recorder id, suitable for
+/// `IDiscMaster2::Item` lookups.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceEvent {
+    Added(String),
+    Removed(String),
+}
+
+// Sink decoding `DDiscMaster2Events` notifications into `DeviceEvent`s.
+#[implement(DDiscMaster2Events)]
+struct MasterEventSink {
+    deliver: Box<dyn Fn(DeviceEvent) + Send>,
+}
+
+impl DDiscMaster2Events_Impl for MasterEventSink {
+    fn NotifyDeviceAdded(&self, _object: Option<&IDispatch>, uniqueid: &BSTR) -> ComResult<()> {
+        (self.deliver)(DeviceEvent::Added(bstr_to_string(uniqueid)));
+        Ok(())
+    }
+
+    fn NotifyDeviceRemoved(&self, _object: Option<&IDispatch>, uniqueid: &BSTR) -> ComResult<()> {
+        (self.deliver)(DeviceEvent::Removed(bstr_to_string(uniqueid)));
+        Ok(())
+    }
+}
+
+impl IDispatch_Impl for MasterEventSink {
+    fn GetTypeInfoCount(&self) -> ComResult<u32> {
+        Ok(0)
+    }
+
+    fn GetTypeInfo(&self, _itinfo: u32, _lcid: u32) -> ComResult<ITypeInfo> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn GetIDsOfNames(
+        &self,
+        _riid: *const GUID,
+        _rgsznames: *const PCWSTR,
+        _cnames: u32,
+        _lcid: u32,
+        _rgdispid: *mut i32,
+    ) -> ComResult<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn Invoke(
+        &self,
+        dispidmember: i32,
+        _riid: *const GUID,
+        _lcid: u32,
+        _wflags: DISPATCH_FLAGS,
+        pdispparams: *const DISPPARAMS,
+        _pvarresult: *mut VARIANT,
+        _pexcepinfo: *mut EXCEPINFO,
+        _puargerr: *mut u32,
+    ) -> ComResult<()> {
+        let params = unsafe { pdispparams.as_ref() }.ok_or_else(|| Error::from(E_POINTER))?;
+        if params.cArgs != 2 {
+            return Err(DISP_E_BADPARAMCOUNT.into());
+        }
+        // Dispatch arguments are stored right to left.
+        let args = unsafe { std::slice::from_raw_parts(params.rgvarg, params.cArgs as usize) };
+        let unique_id = variant_to_bstr(&args[0]).unwrap_or_default();
+        match dispidmember {
+            DISPID_DDISCMASTER2EVENTS_DEVICE_ADDED => self.NotifyDeviceAdded(None, &unique_id),
+            DISPID_DDISCMASTER2EVENTS_DEVICE_REMOVED => self.NotifyDeviceRemoved(None, &unique_id),
+            _ => Err(DISP_E_MEMBERNOTFOUND.into()),
+        }
+    }
+}
+
+/// Channel-based device watcher: forwards `DeviceEvent`s from `master` into
+/// `sender` for as long as the watcher is alive. Events are delivered while
+/// the advising thread pumps messages.
+pub struct DeviceWatcher {
+    _cookie: EventCookie,
+}
+
+impl DeviceWatcher {
+    pub fn advise(
+        master: &IDiscMaster2,
+        sender: mpsc::Sender<DeviceEvent>,
+    ) -> Result<DeviceWatcher, BurnError> {
+        let sink: DDiscMaster2Events = MasterEventSink {
+            deliver: Box::new(move |event| {
+                let _ = sender.send(event);
+            }),
+        }
+        .into();
+        let cookie =
+            EventCookie::advise::<DDiscMaster2Events, _>(master, &sink.cast::<IUnknown>()?)?;
+        Ok(DeviceWatcher { _cookie: cookie })
+    }
+}
+
+/// Async counterpart of `DeviceWatcher`: a stream of `DeviceEvent`s backed
+/// by a dedicated STA thread pumping the COM connection point.
+///
+/// The thread is unadvised and joined when the stream is dropped.
+///
+/// ```no_run
+/// # async fn doc() -> Result<(), imapi_utils::BurnError> {
+/// use imapi_utils::{device_event_stream, DeviceEvent};
+///
+/// let mut events = device_event_stream()?;
+/// while let Some(event) = events.recv().await {
+///     if let DeviceEvent::Added(id) = event {
+///         println!("new burner: {}", id);
+///         break;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct DeviceEventStream {
+    receiver: tokio::sync::mpsc::UnboundedReceiver<DeviceEvent>,
+    pump_thread_id: u32,
+    pump: Option<std::thread::JoinHandle<()>>,
+}
+
+/// Starts a device watcher on its own STA thread and returns the event
+/// stream.
+pub fn device_event_stream() -> Result<DeviceEventStream, BurnError> {
+    let (event_tx, event_rx) = tokio::sync::mpsc::unbounded_channel();
+    // One-shot back channel reporting the pump thread id, or the error that
+    // kept the watcher from starting.
+    let (ready_tx, ready_rx) = mpsc::channel::<Result<u32, BurnError>>();
+
+    let pump = std::thread::spawn(move || {
+        let setup = (|| -> Result<(ComApartment, EventCookie), BurnError> {
+            let apartment = ComApartment::enter()?;
+            let master = new_disc_master2()?;
+            let sink: DDiscMaster2Events = MasterEventSink {
+                deliver: Box::new(move |event| {
+                    let _ = event_tx.send(event);
+                }),
+            }
+            .into();
+            let cookie =
+                EventCookie::advise::<DDiscMaster2Events, _>(&master, &sink.cast::<IUnknown>()?)?;
+            Ok((apartment, cookie))
+        })();
+        let _guards = match setup {
+            Ok(guards) => {
+                let _ = ready_tx.send(Ok(unsafe { GetCurrentThreadId() }));
+                guards
+            }
+            Err(err) => {
+                let _ = ready_tx.send(Err(err));
+                return;
+            }
+        };
+        // STA event delivery needs a message pump; WM_QUIT posted by the
+        // stream's Drop breaks the loop and releases the guards.
+        let mut message = MSG::default();
+        while unsafe { GetMessageW(&mut message, None, 0, 0) }.as_bool() {
+            unsafe { DispatchMessageW(&message) };
+        }
+    });
+
+    let pump_thread_id = ready_rx
+        .recv()
+        .map_err(|_| BurnError::Unsupported("the device watcher thread died during setup"))??;
+    Ok(DeviceEventStream {
+        receiver: event_rx,
+        pump_thread_id,
+        pump: Some(pump),
+    })
+}
+
+impl DeviceEventStream {
+    /// Waits for the next device event; `None` once the watcher shut down.
+    pub async fn recv(&mut self) -> Option<DeviceEvent> {
+        self.receiver.recv().await
+    }
+}
+
+impl futures_core::Stream for DeviceEventStream {
+    type Item = DeviceEvent;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<DeviceEvent>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for DeviceEventStream {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = PostThreadMessageW(self.pump_thread_id, WM_QUIT, None, None);
+        }
+        if let Some(pump) = self.pump.take() {
+            let _ = pump.join();
+        }
+    }
+}